@@ -78,7 +78,7 @@ pub fn initialize(meta: EditorMeta, ctx: &mut Context, servers: Vec<ServerId>) {
                                 normalizes_line_endings: Some(false),
                                 change_annotation_support: Some(
                                     ChangeAnnotationWorkspaceEditClientCapabilities {
-                                        groups_on_label: None,
+                                        groups_on_label: Some(true),
                                     },
                                 ),
                             }),
@@ -100,7 +100,7 @@ pub fn initialize(meta: EditorMeta, ctx: &mut Context, servers: Vec<ServerId>) {
                             execute_command: Some(DynamicRegistrationClientCapabilities {
                                 dynamic_registration: Some(false),
                             }),
-                            workspace_folders: Some(false),
+                            workspace_folders: Some(true),
                             configuration: Some(true),
                             semantic_tokens: enum_primitive::Option::Some(
                                 SemanticTokensWorkspaceClientCapabilities {
@@ -110,12 +110,24 @@ pub fn initialize(meta: EditorMeta, ctx: &mut Context, servers: Vec<ServerId>) {
                             code_lens: Some(CodeLensWorkspaceClientCapabilities {
                                 refresh_support: Some(true),
                             }),
-                            file_operations: None,
-                            inline_value: None,
+                            file_operations: Some(WorkspaceFileOperationsClientCapabilities {
+                                dynamic_registration: Some(true),
+                                did_create: Some(true),
+                                will_create: Some(false),
+                                did_rename: Some(true),
+                                will_rename: Some(true),
+                                did_delete: Some(true),
+                                will_delete: Some(false),
+                            }),
+                            inline_value: Some(InlineValueWorkspaceClientCapabilities {
+                                refresh_support: Some(true),
+                            }),
                             inlay_hint: Some(InlayHintWorkspaceClientCapabilities {
                                 refresh_support: Some(true),
                             }),
-                            diagnostic: None,
+                            diagnostic: Some(DiagnosticWorkspaceClientCapabilities {
+                                refresh_support: Some(true),
+                            }),
                         }),
                         text_document: Some(TextDocumentClientCapabilities {
                             synchronization: Some(TextDocumentSyncClientCapabilities {
@@ -128,15 +140,17 @@ pub fn initialize(meta: EditorMeta, ctx: &mut Context, servers: Vec<ServerId>) {
                                 dynamic_registration: Some(false),
                                 completion_item: Some(CompletionItemCapability {
                                     snippet_support: Some(ctx.config.snippet_support),
-                                    commit_characters_support: Some(false),
+                                    commit_characters_support: Some(true),
                                     documentation_format: Some(vec![
                                         MarkupKind::Markdown,
                                         MarkupKind::PlainText,
                                     ]),
-                                    deprecated_support: Some(false),
-                                    preselect_support: Some(false),
-                                    tag_support: None,
-                                    insert_replace_support: None,
+                                    deprecated_support: Some(true),
+                                    preselect_support: Some(true),
+                                    tag_support: Some(TagSupport {
+                                        value_set: vec![CompletionItemTag::DEPRECATED],
+                                    }),
+                                    insert_replace_support: Some(true),
                                     resolve_support: Some(CompletionItemCapabilityResolveSupport {
                                         properties: vec![
                                             "additionalTextEdits".to_string(),
@@ -145,7 +159,7 @@ pub fn initialize(meta: EditorMeta, ctx: &mut Context, servers: Vec<ServerId>) {
                                         ],
                                     }),
                                     insert_text_mode_support: None,
-                                    label_details_support: None,
+                                    label_details_support: Some(true),
                                 }),
                                 completion_item_kind: Some(CompletionItemKindCapability {
                                     value_set: Some(vec![
@@ -176,9 +190,16 @@ pub fn initialize(meta: EditorMeta, ctx: &mut Context, servers: Vec<ServerId>) {
                                         CompletionItemKind::TYPE_PARAMETER,
                                     ]),
                                 }),
-                                context_support: Some(false),
+                                context_support: Some(true),
                                 insert_text_mode: None,
-                                completion_list: None,
+                                completion_list: Some(CompletionListCapability {
+                                    item_defaults: Some(vec![
+                                        "commitCharacters".to_string(),
+                                        "editRange".to_string(),
+                                        "insertTextFormat".to_string(),
+                                        "data".to_string(),
+                                    ]),
+                                }),
                             }),
                             hover: Some(HoverClientCapabilities {
                                 dynamic_registration: Some(false),
@@ -254,29 +275,31 @@ pub fn initialize(meta: EditorMeta, ctx: &mut Context, servers: Vec<ServerId>) {
                                         .collect(),
                                     },
                                 }),
-                                is_preferred_support: Some(false),
+                                is_preferred_support: Some(true),
                                 disabled_support: None,
                                 data_support: None,
                                 resolve_support: Some(CodeActionCapabilityResolveSupport {
                                     properties: ["edit"].iter().map(|s| s.to_string()).collect(),
                                 }),
-                                honors_change_annotations: None,
+                                honors_change_annotations: Some(true),
                             }),
                             code_lens: Some(DynamicRegistrationClientCapabilities {
                                 dynamic_registration: Some(false),
                             }),
                             document_link: Some(DocumentLinkClientCapabilities {
                                 dynamic_registration: Some(false),
-                                tooltip_support: Some(false),
+                                tooltip_support: Some(true),
                             }),
                             color_provider: Some(DynamicRegistrationClientCapabilities {
                                 dynamic_registration: Some(false),
                             }),
                             rename: Some(RenameClientCapabilities {
                                 dynamic_registration: Some(false),
-                                prepare_support: Some(false),
-                                prepare_support_default_behavior: None,
-                                honors_change_annotations: None,
+                                prepare_support: Some(true),
+                                prepare_support_default_behavior: Some(
+                                    PrepareSupportDefaultBehavior::IDENTIFIER,
+                                ),
+                                honors_change_annotations: Some(true),
                             }),
                             publish_diagnostics: Some(PublishDiagnosticsClientCapabilities {
                                 related_information: Some(true),
@@ -286,19 +309,35 @@ pub fn initialize(meta: EditorMeta, ctx: &mut Context, servers: Vec<ServerId>) {
                                         DiagnosticTag::DEPRECATED,
                                     ],
                                 }),
-                                version_support: None,
-                                code_description_support: None,
-                                data_support: None,
+                                version_support: Some(true),
+                                code_description_support: Some(true),
+                                data_support: Some(true),
+                            }),
+                            folding_range: Some(FoldingRangeClientCapabilities {
+                                dynamic_registration: Some(false),
+                                range_limit: None,
+                                line_folding_only: Some(false),
+                                folding_range_kind: Some(FoldingRangeKindCapability {
+                                    value_set: Some(vec![
+                                        FoldingRangeKind::Comment,
+                                        FoldingRangeKind::Imports,
+                                        FoldingRangeKind::Region,
+                                    ]),
+                                }),
+                                folding_range: Some(FoldingRangeCapability {
+                                    collapsed_text: Some(false),
+                                }),
                             }),
-                            folding_range: None,
                             selection_range: Some(SelectionRangeClientCapabilities {
                                 dynamic_registration: None,
                             }),
                             semantic_tokens: Some(SemanticTokensClientCapabilities {
                                 dynamic_registration: Some(true),
                                 requests: SemanticTokensClientCapabilitiesRequests {
-                                    range: Some(false),
-                                    full: Some(SemanticTokensFullOptions::Bool(true)),
+                                    range: Some(true),
+                                    full: Some(SemanticTokensFullOptions::Delta {
+                                        delta: Some(true),
+                                    }),
                                 },
                                 token_types: semantic_tokens_config(&ctx.config, &meta)
                                     .iter()
@@ -321,18 +360,27 @@ pub fn initialize(meta: EditorMeta, ctx: &mut Context, servers: Vec<ServerId>) {
                                 augments_syntax_tokens: None,
                                 server_cancel_support: Some(true),
                             }),
-                            linked_editing_range: None,
+                            linked_editing_range: Some(LinkedEditingRangeClientCapabilities {
+                                dynamic_registration: Some(false),
+                            }),
                             call_hierarchy: Some(CallHierarchyClientCapabilities {
                                 dynamic_registration: Some(false),
                             }),
                             moniker: None,
-                            inline_value: None,
-                            type_hierarchy: None,
+                            inline_value: Some(InlineValueClientCapabilities {
+                                dynamic_registration: Some(false),
+                            }),
+                            type_hierarchy: Some(TypeHierarchyClientCapabilities {
+                                dynamic_registration: Some(false),
+                            }),
                             inlay_hint: Some(InlayHintClientCapabilities {
                                 dynamic_registration: Some(false),
                                 resolve_support: None,
                             }),
-                            diagnostic: None,
+                            diagnostic: Some(DiagnosticClientCapabilities {
+                                dynamic_registration: Some(true),
+                                related_document_support: Some(true),
+                            }),
                             inline_completion: None,
                         }),
                         window: Some(WindowClientCapabilities {
@@ -342,7 +390,7 @@ pub fn initialize(meta: EditorMeta, ctx: &mut Context, servers: Vec<ServerId>) {
                                     additional_properties_support: Some(true),
                                 }),
                             }),
-                            show_document: None,
+                            show_document: Some(ShowDocumentClientCapabilities { support: true }),
                         }),
                         general: Some(GeneralClientCapabilities {
                             regular_expressions: Some(RegularExpressionsClientCapabilities {
@@ -456,13 +504,22 @@ pub const CAPABILITY_CODE_ACTIONS_RESOLVE: &str = "lsp-code-actions-resolve";
 pub const CAPABILITY_CODE_LENS: &str = "lsp-code-lens";
 pub const CAPABILITY_COMPLETION: &str = "lsp-completion (hooked on InsertIdle)";
 pub const CAPABILITY_DEFINITION: &str = "lsp-definition";
+pub const CAPABILITY_DOCUMENT_COLOR: &str =
+    "lsp-document-colors-enable, lsp-color-presentation-next";
 pub const CAPABILITY_DOCUMENT_HIGHLIGHT: &str = "lsp-highlight-references";
+pub const CAPABILITY_DOCUMENT_LINK: &str = "lsp-document-link, lsp-document-links";
 pub const CAPABILITY_DOCUMENT_SYMBOL: &str = "lsp-document-symbol";
 pub const CAPABILITY_EXECUTE_COMMAND: &str = "lsp-execute-command";
+pub const CAPABILITY_FOLDING_RANGE: &str =
+    "lsp-folding-ranges, lsp-folding-range-select, lsp-next-folding-range, lsp-previous-folding-range";
 pub const CAPABILITY_FORMATTING: &str = "lsp-formatting";
 pub const CAPABILITY_HOVER: &str = "lsp-hover";
 pub const CAPABILITY_IMPLEMENTATION: &str = "lsp-implementation";
 pub const CAPABILITY_INLAY_HINTS: &str = "lsp-inlay-hints";
+pub const CAPABILITY_INLINE_VALUES: &str = "lsp-inline-values";
+pub const CAPABILITY_LINKED_EDITING_RANGE: &str = "lsp-linked-editing-range";
+pub const CAPABILITY_ON_TYPE_FORMATTING: &str = "lsp-on-type-formatting (hooked on InsertChar)";
+pub const CAPABILITY_PULL_DIAGNOSTICS: &str = "lsp-diagnostics (pull)";
 pub const CAPABILITY_RANGE_FORMATTING: &str = "lsp-range-formatting";
 pub const CAPABILITY_REFERENCES: &str = "lsp-references";
 pub const CAPABILITY_RENAME: &str = "lsp-rename";
@@ -470,6 +527,7 @@ pub const CAPABILITY_SELECTION_RANGE: &str = "lsp-selection-range";
 pub const CAPABILITY_SEMANTIC_TOKENS: &str = "lsp-semantic-tokens";
 pub const CAPABILITY_SIGNATURE_HELP: &str = "lsp-signature-help";
 pub const CAPABILITY_TYPE_DEFINITION: &str = "lsp-type-definition";
+pub const CAPABILITY_WORKSPACE_PULL_DIAGNOSTICS: &str = "lsp-diagnostics (workspace pull)";
 pub const CAPABILITY_WORKSPACE_SYMBOL: &str = "lsp-workspace-symbol";
 
 pub const CAPABILITY_TEXT_DOCUMENT_BUILD: &str = "texlab-build";
@@ -543,16 +601,27 @@ pub fn server_has_capability(
             Some(OneOf::Right(_)) => true,
             None => false,
         },
+        CAPABILITY_DOCUMENT_COLOR => match server_capabilities.color_provider {
+            Some(ColorProviderCapability::Simple(ok)) => ok,
+            Some(_) => true,
+            None => false,
+        },
         CAPABILITY_DOCUMENT_HIGHLIGHT => match server_capabilities.document_highlight_provider {
             Some(OneOf::Left(ok)) => ok,
             Some(OneOf::Right(_)) => true,
             None => false,
         },
+        CAPABILITY_DOCUMENT_LINK => server_capabilities.document_link_provider.is_some(),
         CAPABILITY_DOCUMENT_SYMBOL => match server_capabilities.document_symbol_provider {
             Some(OneOf::Left(ok)) => ok,
             Some(OneOf::Right(_)) => true,
             None => false,
         },
+        CAPABILITY_FOLDING_RANGE => match server_capabilities.folding_range_provider {
+            Some(FoldingRangeProviderCapability::Simple(ok)) => ok,
+            Some(_) => true,
+            None => false,
+        },
         CAPABILITY_FORMATTING => match server_capabilities.document_formatting_provider {
             Some(OneOf::Left(ok)) => ok,
             Some(OneOf::Right(_)) => true,
@@ -573,6 +642,17 @@ pub fn server_has_capability(
             Some(OneOf::Right(_)) => true,
             None => false,
         },
+        CAPABILITY_LINKED_EDITING_RANGE => {
+            match server_capabilities.linked_editing_range_provider {
+                Some(LinkedEditingRangeServerCapabilities::Simple(ok)) => ok,
+                Some(_) => true,
+                None => false,
+            }
+        }
+        CAPABILITY_ON_TYPE_FORMATTING => server_capabilities
+            .document_on_type_formatting_provider
+            .is_some(),
+        CAPABILITY_PULL_DIAGNOSTICS => server_capabilities.diagnostic_provider.is_some(),
         CAPABILITY_RANGE_FORMATTING => match server_capabilities.document_range_formatting_provider
         {
             Some(OneOf::Left(ok)) => ok,
@@ -601,6 +681,13 @@ pub fn server_has_capability(
             Some(_) => true,
             None => false,
         },
+        CAPABILITY_WORKSPACE_PULL_DIAGNOSTICS => match &server_capabilities.diagnostic_provider {
+            Some(DiagnosticServerCapabilities::Options(options)) => options.workspace_diagnostics,
+            Some(DiagnosticServerCapabilities::RegistrationOptions(options)) => {
+                options.diagnostic_options.workspace_diagnostics
+            }
+            None => false,
+        },
         CAPABILITY_WORKSPACE_SYMBOL => match server_capabilities.workspace_symbol_provider {
             Some(OneOf::Left(ok)) => ok,
             Some(OneOf::Right(_)) => true,
@@ -644,6 +731,12 @@ pub fn capabilities(meta: EditorMeta, ctx: &mut Context) {
         let server_name = &server_settings.name;
 
         probe_feature(to_editor, entry, &mut features, CAPABILITY_SELECTION_RANGE);
+        probe_feature(
+            to_editor,
+            entry,
+            &mut features,
+            CAPABILITY_LINKED_EDITING_RANGE,
+        );
         probe_feature(to_editor, entry, &mut features, CAPABILITY_HOVER);
         probe_feature(to_editor, entry, &mut features, CAPABILITY_COMPLETION);
         probe_feature(to_editor, entry, &mut features, CAPABILITY_SIGNATURE_HELP);
@@ -664,8 +757,17 @@ pub fn capabilities(meta: EditorMeta, ctx: &mut Context) {
                 .push(server_name);
         }
         probe_feature(to_editor, entry, &mut features, CAPABILITY_WORKSPACE_SYMBOL);
+        probe_feature(to_editor, entry, &mut features, CAPABILITY_FOLDING_RANGE);
+        probe_feature(to_editor, entry, &mut features, CAPABILITY_DOCUMENT_COLOR);
+        probe_feature(to_editor, entry, &mut features, CAPABILITY_DOCUMENT_LINK);
         probe_feature(to_editor, entry, &mut features, CAPABILITY_FORMATTING);
         probe_feature(to_editor, entry, &mut features, CAPABILITY_RANGE_FORMATTING);
+        probe_feature(
+            to_editor,
+            entry,
+            &mut features,
+            CAPABILITY_ON_TYPE_FORMATTING,
+        );
         probe_feature(to_editor, entry, &mut features, CAPABILITY_RENAME);
         probe_feature(to_editor, entry, &mut features, CAPABILITY_CODE_ACTIONS);
         probe_feature(
@@ -681,6 +783,7 @@ pub fn capabilities(meta: EditorMeta, ctx: &mut Context) {
             .or_default()
             .push(server_name);
         probe_feature(to_editor, entry, &mut features, CAPABILITY_INLAY_HINTS);
+        probe_feature(to_editor, entry, &mut features, CAPABILITY_INLINE_VALUES);
 
         // NOTE controller should park request for capabilities until they are available thus it should
         // be safe to unwrap here (otherwise something unexpectedly wrong and it's better to panic)
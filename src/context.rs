@@ -2,6 +2,7 @@ use crate::editor_transport::{self, ToEditorSender};
 use crate::language_server_transport::LanguageServerTransport;
 use crate::text_sync::CompiledFileSystemWatcher;
 use crate::thread_worker::Worker;
+use crate::workspace::PendingWorkspaceEdit;
 use crate::{filetype_to_language_id_map, types::*};
 use jsonrpc_core::{self, Call, Error, Failure, Id, Output, Success, Value, Version};
 use lsp_types::notification::{Cancel, Notification};
@@ -36,7 +37,7 @@ pub enum RequestParams<T> {
 
 pub type ResponsesCallback =
     Box<dyn FnOnce(&mut Context, EditorMeta, Vec<(ServerId, Value)>) -> ()>;
-type BatchNumber = usize;
+pub type BatchNumber = usize;
 type BatchCount = BatchNumber;
 
 pub struct OutstandingRequests {
@@ -66,6 +67,8 @@ pub struct Context {
     pub batch_sizes: HashMap<BatchNumber, HashMap<ServerId, usize>>,
     pub batches:
         HashMap<BatchNumber, (Vec<(ServerId, serde_json::value::Value)>, ResponsesCallback)>,
+    // Batches that wait for a response from every server, see call_to_completion.
+    pub batches_to_complete: HashSet<BatchNumber>,
     pub buffer_tombstones: HashSet<String>,
     pub server_tombstones: HashSet<String>,
     pub code_lenses: HashMap<String, Vec<(ServerId, CodeLens)>>,
@@ -75,18 +78,27 @@ pub struct Context {
     // might need to hook into ClientClose). Track the client name, so we can check if the
     // completions are valid.
     pub completion_last_client: Option<ClientId>,
+    // Servers whose last completion list was incomplete, with the buffer and the start of the
+    // completed word. Re-requesting completions there tells them to complete the list.
+    pub completion_incomplete: Option<(String, KakounePosition, HashSet<ServerId>)>,
     pub config: Config,
     pub diagnostics: HashMap<String, Vec<(ServerId, Diagnostic)>>,
     pub documents: HashMap<String, Document>,
+    // Result ID and items of the last pulled diagnostics report, by server and file.
+    pub pulled_diagnostics: HashMap<(ServerId, String), (Option<String>, Vec<Diagnostic>)>,
     pub dynamic_config: DynamicConfig,
     pub inlay_hints: HashMap<String, Vec<(ServerId, InlayHint)>>,
     pub language_servers: BTreeMap<ServerId, ServerSettings>,
     pub route_cache: HashMap<(ServerName, RootPath), ServerId>,
+    // Result ID and data of the last full semantic tokens result, by server and file.
+    pub semantic_tokens: HashMap<(ServerId, String), (Option<String>, Vec<SemanticToken>)>,
     pub outstanding_requests:
         HashMap<(ServerId, &'static str, String, Option<ClientId>), OutstandingRequests>,
     pub pending_requests: Vec<EditorRequest>,
     pub pending_requests_from_future: Vec<EditorRequest>,
     pub pending_message_requests: VecDeque<(Id, ServerId, ShowMessageRequestParams)>,
+    // Workspace edit shown in a preview buffer, waiting for the user to accept or reject it.
+    pub pending_workspace_edit: Option<PendingWorkspaceEdit>,
     pub request_counter: u64,
     pub response_waitlist: HashMap<Id, (EditorMeta, &'static str, BatchNumber, bool)>,
     pub session: SessionId,
@@ -109,23 +121,28 @@ impl Context {
             batch_count: 0,
             batch_sizes: Default::default(),
             batches: Default::default(),
+            batches_to_complete: Default::default(),
             buffer_tombstones: Default::default(),
             server_tombstones: Default::default(),
             code_lenses: Default::default(),
             completion_items: vec![],
             completion_items_timestamp: i32::MAX,
             completion_last_client: None,
+            completion_incomplete: None,
             config,
             diagnostics: Default::default(),
             documents: Default::default(),
+            pulled_diagnostics: Default::default(),
             dynamic_config: DynamicConfig::default(),
             inlay_hints: Default::default(),
             language_servers: BTreeMap::new(),
             route_cache: HashMap::new(),
+            semantic_tokens: Default::default(),
             outstanding_requests: HashMap::default(),
             pending_requests: vec![],
             pending_requests_from_future: vec![],
             pending_message_requests: VecDeque::new(),
+            pending_workspace_edit: None,
             request_counter: 0,
             response_waitlist: HashMap::default(),
             session,
@@ -160,6 +177,32 @@ impl Context {
             .iter()
             .map(move |&server_id| (server_id, self.server(server_id)))
     }
+    /// Adds a server whose messages are forwarded to the returned receiver.
+    #[cfg(test)]
+    pub fn add_mock_server(
+        &mut self,
+        root: &str,
+        capabilities: ServerCapabilities,
+    ) -> (ServerId, crossbeam_channel::Receiver<ServerMessage>) {
+        let (transport, messages) = crate::language_server_transport::mock(self.to_editor.clone());
+        let server_id = self.language_servers.len();
+        self.language_servers.insert(
+            server_id,
+            ServerSettings {
+                name: format!("server{}", server_id),
+                roots: vec![root.to_string()],
+                single_instance: None,
+                offset_encoding: OffsetEncoding::Utf16,
+                preferred_offset_encoding: None,
+                transport,
+                capabilities: Some(capabilities),
+                settings: None,
+                workaround_eslint: false,
+            },
+        );
+        (server_id, messages)
+    }
+
     pub fn server(&self, server_id: ServerId) -> &ServerSettings {
         &self.language_servers[&server_id]
     }
@@ -190,7 +233,67 @@ impl Context {
         R::Params: IntoParams + Clone,
         R::Result: for<'a> Deserialize<'a>,
     {
-        let ops = match params {
+        let ops = self.request_ops::<R>(&meta, params);
+        self.batch_call::<R>(
+            meta,
+            ops,
+            false,
+            Box::new(move |ctx, meta, vals| {
+                let results = vals
+                    .into_iter()
+                    .map(|(server_id, val)| {
+                        (
+                            server_id,
+                            serde_json::from_value(val).expect("Failed to parse response"),
+                        )
+                    })
+                    .collect();
+                callback(ctx, meta, results)
+            }),
+        );
+    }
+
+    /// Like call, but the requests are not de-duplicated against other outstanding requests, so
+    /// none of them is canceled, and a server that responds with an error is passed to the
+    /// callback with a None result. Use it when the callback must run once every server has
+    /// responded.
+    pub fn call_to_completion<
+        R: Request,
+        F: for<'a> FnOnce(&'a mut Context, EditorMeta, Vec<(ServerId, Option<R::Result>)>) -> ()
+            + 'static,
+    >(
+        &mut self,
+        meta: EditorMeta,
+        params: RequestParams<R::Params>,
+        callback: F,
+    ) where
+        R::Params: IntoParams + Clone,
+        R::Result: for<'a> Deserialize<'a>,
+    {
+        let ops = self.request_ops::<R>(&meta, params);
+        self.batch_call::<R>(
+            meta,
+            ops,
+            true,
+            Box::new(move |ctx, meta, vals| {
+                let results = vals
+                    .into_iter()
+                    .map(|(server_id, val)| (server_id, serde_json::from_value(val).ok()))
+                    .collect();
+                callback(ctx, meta, results)
+            }),
+        );
+    }
+
+    fn request_ops<R: Request>(
+        &self,
+        meta: &EditorMeta,
+        params: RequestParams<R::Params>,
+    ) -> Vec<(ServerId, R::Params)>
+    where
+        R::Params: Clone,
+    {
+        match params {
             RequestParams::All(params) => {
                 let mut ops = Vec::with_capacity(params.len() * meta.servers.len());
                 for &server_id in &meta.servers {
@@ -209,31 +312,22 @@ impl Context {
                     ops
                 })
                 .collect(),
-        };
-        self.batch_call::<R, _>(
-            meta,
-            ops,
-            Box::new(
-                move |ctx: &mut Context, meta: EditorMeta, results: Vec<(ServerId, R::Result)>| {
-                    callback(ctx, meta, results)
-                },
-            ),
-        );
+        }
     }
 
-    fn batch_call<
-        R: Request,
-        F: for<'a> FnOnce(&'a mut Context, EditorMeta, Vec<(ServerId, R::Result)>) -> () + 'static,
-    >(
+    fn batch_call<R: Request>(
         &mut self,
         meta: EditorMeta,
         ops: Vec<(ServerId, R::Params)>,
-        callback: F,
+        to_completion: bool,
+        callback: ResponsesCallback,
     ) where
         R::Params: IntoParams,
-        R::Result: for<'a> Deserialize<'a>,
     {
         let batch_id = self.next_batch_id();
+        if to_completion {
+            self.batches_to_complete.insert(batch_id);
+        }
 
         self.batch_sizes.insert(
             batch_id,
@@ -243,25 +337,8 @@ impl Context {
                 m
             }),
         );
-        self.batches.insert(
-            batch_id,
-            (
-                Vec::with_capacity(ops.len()),
-                Box::new(move |ctx, meta, vals| {
-                    // Only get the last response of each server.
-                    let results = vals
-                        .into_iter()
-                        .map(|(server_id, val)| {
-                            (
-                                server_id,
-                                serde_json::from_value(val).expect("Failed to parse response"),
-                            )
-                        })
-                        .collect();
-                    callback(ctx, meta, results)
-                }),
-            ),
-        );
+        self.batches
+            .insert(batch_id, (Vec::with_capacity(ops.len()), callback));
         for (server_id, params) in ops {
             let params = params.into_params();
             if params.is_err() {
@@ -272,14 +349,16 @@ impl Context {
             self.response_waitlist
                 .insert(id.clone(), (meta.clone(), R::METHOD, batch_id, false));
 
-            add_outstanding_request(
-                server_id,
-                self,
-                R::METHOD,
-                meta.buffile.clone(),
-                meta.client.clone(),
-                id.clone(),
-            );
+            if !to_completion {
+                add_outstanding_request(
+                    server_id,
+                    self,
+                    R::METHOD,
+                    meta.buffile.clone(),
+                    meta.client.clone(),
+                    id.clone(),
+                );
+            }
 
             let call = jsonrpc_core::MethodCall {
                 jsonrpc: Some(Version::V2),
@@ -30,6 +30,7 @@ use crate::types::*;
 use crate::util::*;
 use crate::workspace::{
     self, EditorApplyEdit, EditorDidChangeConfigurationParams, EditorExecuteCommand,
+    EditorRenameFileParams, EditorWorkspaceEditPreviewResponse, EditorWorkspaceEditPreviewToggle,
 };
 use crate::{context::*, set_logger};
 use crate::{diagnostics, do_cleanup};
@@ -39,6 +40,7 @@ use code_lens::{text_document_code_lens, CodeLensOptions};
 use crossbeam_channel::{after, never, tick, Receiver, Select, Sender};
 use indoc::formatdoc;
 use inlay_hints::{InlayHintApplyParams, InlayHintsOptions};
+use inline_values::InlineValuesOptions;
 use itertools::Itertools;
 use jsonrpc_core::{Call, ErrorCode, MethodCall, Output, Params};
 use lean::EditorPlainGoalParams;
@@ -421,6 +423,37 @@ fn dispatch_fifo_request(
         COMPLETE_EXECUTE_COMMAND => Box::new(CompletionParameters {
             response_fifo: state.next()?,
         }),
+        "kakoune/code-actions-fix-all" => {
+            let num_filters = state.next()?;
+            Box::new(CodeActionsFixAllParams {
+                filters: state.next_vec(num_filters)?,
+            })
+        }
+        "kakoune/color-presentation/next" => Box::new(PositionParams {
+            position: state.next()?,
+        }),
+        "kakoune/diagnostic-related-information" => Box::new(DiagnosticRelatedInformationParams {
+            position: state.next()?,
+            highlight: state.next()?,
+        }),
+        "kakoune/diagnostics-export" => Box::new(DiagnosticsExportParams {
+            session: match state.next::<String>()?.as_str() {
+                "buffer" => false,
+                "session" => true,
+                _ => panic!("invalid request"),
+            },
+            format: match state.next::<String>()?.as_str() {
+                "json" => DiagnosticsExportFormat::Json,
+                "sarif" => DiagnosticsExportFormat::Sarif,
+                "quickfix" => DiagnosticsExportFormat::Quickfix,
+                _ => panic!("invalid request"),
+            },
+            path: state.next()?,
+        }),
+        "kakoune/document-link/open" => Box::new(PositionParams {
+            position: state.next()?,
+        }),
+        "kakoune/document-links" => Box::new(()),
         "kakoune/exit" => Box::new(()),
         "kakoune/goto-document-symbol" => Box::new(GotoSymbolParams {
             goto_symbol: state.next()?,
@@ -439,6 +472,18 @@ fn dispatch_fifo_request(
             },
             kind: inlay_hints::InlayHintApplyKind::Selected,
         }),
+        "kakoune/next-or-previous-diagnostic" => {
+            let num_filters = state.next()?;
+            Box::new(NextOrPrevDiagnosticParams {
+                position: state.next()?,
+                search_next: match state.next::<String>()?.as_str() {
+                    "next" => true,
+                    "previous" => false,
+                    _ => panic!("invalid request"),
+                },
+                filters: state.next_vec(num_filters)?,
+            })
+        }
         "kakoune/next-or-previous-symbol" => {
             let num_symbol_kinds = state.next()?;
             Box::new(NextOrPrevSymbolParams {
@@ -471,6 +516,12 @@ fn dispatch_fifo_request(
         "kakoune/textDocument/codeLens" => Box::new(CodeLensOptions {
             selection_desc: state.next()?,
         }),
+        "kakoune/workspace-edit-preview/respond" => Box::new(EditorWorkspaceEditPreviewResponse {
+            accept: state.next()?,
+        }),
+        "kakoune/workspace-edit-preview/toggle" => Box::new(EditorWorkspaceEditPreviewToggle {
+            group: state.next()?,
+        }),
         "kakoune/did-change-option" => {
             let hook_param = state.next::<String>()?;
             let Some((key, value)) = hook_param.split_once('=') else {
@@ -524,6 +575,15 @@ fn dispatch_fifo_request(
             position: state.next()?,
             completion: EditorCompletion {
                 offset: state.next()?,
+                prefer_replace: state.next()?,
+                kind_icons: state
+                    .next_map()?
+                    .into_iter()
+                    .filter_map(|entry| {
+                        let (kind, icon) = entry.split_once('=')?;
+                        Some((kind.to_string(), icon.to_string()))
+                    })
+                    .collect(),
             },
         }),
         "textDocument/definition" => {
@@ -557,6 +617,11 @@ fn dispatch_fifo_request(
                 position: state.next()?,
             })
         }
+        "textDocument/documentColor" => Box::new(()),
+        "textDocument/documentLink" => Box::new(()),
+        "textDocument/linkedEditingRange" => Box::new(PositionParams {
+            position: state.next()?,
+        }),
         "textDocument/formatting" => {
             let params = Box::new(<FormattingOptions as Deserializable>::deserialize(state)?);
             let is_sync = state.next::<String>()? == "is-sync";
@@ -577,10 +642,19 @@ fn dispatch_fifo_request(
         "textDocument/inlayHint" => Box::new(InlayHintsOptions {
             buf_line_count: state.next()?,
         }),
+        "textDocument/inlineValue" => Box::new(InlineValuesOptions {
+            frame_id: state.next()?,
+            stopped_location: state.next()?,
+            buf_line_count: state.next()?,
+        }),
         "textDocument/prepareCallHierarchy" => Box::new(CallHierarchyParams {
             position: state.next()?,
             incoming_or_outgoing: state.next()?,
         }),
+        "textDocument/prepareTypeHierarchy" => Box::new(TypeHierarchyParams {
+            position: state.next()?,
+            supertypes_or_subtypes: state.next()?,
+        }),
         "textDocument/rangeFormatting" => {
             let params = Box::new(RangeFormattingParams {
                 formatting_options: state.next()?,
@@ -602,9 +676,22 @@ fn dispatch_fifo_request(
                 position: state.next()?,
             })
         }
+        "textDocument/foldingRange" => Box::new(FoldingRangeEditorParams {
+            position: state.next()?,
+            action: state.next()?,
+        }),
+        "textDocument/onTypeFormatting" => Box::new(OnTypeFormattingParams {
+            position: state.next()?,
+            ch: state.next()?,
+            formatting_options: state.next()?,
+        }),
+        "textDocument/prepareRename" => Box::new(PositionParams {
+            position: state.next()?,
+        }),
         "textDocument/rename" => Box::new(TextDocumentRenameParams {
             position: state.next()?,
             new_name: state.next()?,
+            preview: state.next()?,
         }),
         "textDocument/selectionRange" => Box::new(SelectionRangePositionParams {
             position: state.next()?,
@@ -617,6 +704,17 @@ fn dispatch_fifo_request(
             position: state.next()?,
         }),
         "textDocument/semanticTokens/full" => Box::new(()),
+        "textDocument/semanticTokens/range" => {
+            // %val{window_range}: line column height width
+            let first_line = state.next()?;
+            let _column: u32 = state.next()?;
+            let line_count = state.next()?;
+            let _width: u32 = state.next()?;
+            Box::new(SemanticTokensRangeEditorParams {
+                first_line,
+                line_count,
+            })
+        }
         "textDocument/switchSourceHeader" => Box::new(()),
         "window/showMessageRequest/showNext" => Box::new(()),
         "window/showMessageRequest/respond" => Box::new(MessageRequestResponse {
@@ -646,6 +744,9 @@ fn dispatch_fifo_request(
             sync_trailer(state, is_sync)?;
             params
         }
+        "workspace/willRenameFiles" => Box::new(EditorRenameFileParams {
+            new_path: state.next()?,
+        }),
         "workspace/symbol" => {
             buffile = state.next()?;
             filetype.clear();
@@ -1048,146 +1149,8 @@ pub fn start(
                         }
                     },
                     ServerMessage::Response(output) => {
-                        match output {
-                            Output::Success(success) => {
-                                if let Some((meta, method, batch_id, canceled)) =
-                                    ctx.response_waitlist.remove(&success.id)
-                                {
-                                    if canceled {
-                                        continue;
-                                    }
-                                    remove_outstanding_request(
-                                        server_id,
-                                        ctx,
-                                        method,
-                                        meta.buffile.clone(),
-                                        meta.client.clone(),
-                                        &success.id,
-                                    );
-                                    if let Some((mut vals, callback)) =
-                                        ctx.batches.remove(&batch_id)
-                                    {
-                                        if let Some(batch_seq) = ctx.batch_sizes.remove(&batch_id) {
-                                            vals.push((server_id, success.result));
-                                            let batch_size: usize = batch_seq.values().sum();
-
-                                            if vals.len() >= batch_size {
-                                                callback(ctx, meta, vals);
-                                                if ctx.is_exiting {
-                                                    break 'event_loop;
-                                                }
-                                            } else {
-                                                ctx.batch_sizes.insert(batch_id, batch_seq);
-                                                ctx.batches.insert(batch_id, (vals, callback));
-                                            }
-                                        }
-                                    }
-                                } else {
-                                    error!(
-                                        ctx.to_editor(),
-                                        "Id {:?} is not in waitlist!", success.id
-                                    );
-                                }
-                            }
-                            Output::Failure(failure) => {
-                                if let Some(request) = ctx.response_waitlist.remove(&failure.id) {
-                                    let (meta, method, batch_id, canceled) = request;
-                                    if canceled {
-                                        continue;
-                                    }
-                                    remove_outstanding_request(
-                                        server_id,
-                                        ctx,
-                                        method,
-                                        meta.buffile.clone(),
-                                        meta.client.clone(),
-                                        &failure.id,
-                                    );
-                                    if failure.error.code
-                                        == ErrorCode::ServerError(CONTENT_MODIFIED)
-                                    {
-                                        debug!(
-                                            ctx.to_editor(),
-                                            "Error response from server {}: {:?}",
-                                            &ctx.server(server_id).name,
-                                            failure
-                                        );
-                                    } else {
-                                        error!(
-                                            ctx.to_editor(),
-                                            "Error response from server {}: {:?}",
-                                            &ctx.server(server_id).name,
-                                            failure
-                                        );
-                                    }
-                                    if let Some((vals, callback)) = ctx.batches.remove(&batch_id) {
-                                        if let Some(mut batch_seq) =
-                                            ctx.batch_sizes.remove(&batch_id)
-                                        {
-                                            batch_seq.remove(&server_id);
-
-                                            // We con only keep going if there are still other servers to respond.
-                                            // Otherwise, skip the following block and handle failure.
-                                            if !batch_seq.is_empty() {
-                                                // Remove this failing language server from the batch, allowing
-                                                // working ones to still be handled.
-                                                let vals: Vec<_> = vals
-                                                    .into_iter()
-                                                    .filter(|(s, _)| *s != server_id)
-                                                    .collect();
-
-                                                // Scenario: this failing server is holding back the response handling
-                                                // for all other servers, which already responded successfully.
-                                                let batch_size: usize = batch_seq.values().sum();
-                                                if vals.len() >= batch_size {
-                                                    callback(ctx, meta, vals);
-                                                    if ctx.is_exiting {
-                                                        break 'event_loop;
-                                                    }
-                                                } else {
-                                                    // Re-insert the batch, as we have no business with it at the moment,
-                                                    // since not all servers have completely responded.
-                                                    ctx.batch_sizes.insert(batch_id, batch_seq);
-                                                    ctx.batches.insert(batch_id, (vals, callback));
-                                                }
-
-                                                continue;
-                                            }
-                                        }
-                                    }
-                                    match failure.error.code {
-                                        code if code
-                                            == ErrorCode::ServerError(CONTENT_MODIFIED)
-                                            || method == request::CodeActionRequest::METHOD => {}
-                                        code => {
-                                            let msg = match code {
-                                                ErrorCode::MethodNotFound => format!(
-                                                    "language server {} doesn't support method {}",
-                                                    &ctx.server(server_id).name,
-                                                    method
-                                                ),
-                                                _ => format!(
-                                                    "language server {} error: {}",
-                                                    &ctx.server(server_id).name,
-                                                    editor_quote(&failure.error.message)
-                                                ),
-                                            };
-                                            ctx.show_error(meta, msg);
-                                        }
-                                    }
-                                } else {
-                                    error!(
-                                        ctx.to_editor(),
-                                        "Error response from server {}: {:?}",
-                                        &ctx.server(server_id).name,
-                                        failure
-                                    );
-                                    error!(
-                                        ctx.to_editor(),
-                                        "Id {:?} is not in waitlist!", failure.id
-                                    );
-                                }
-                            }
+                        if process_server_response(ctx, server_id, output).is_break() {
+                            break 'event_loop;
                         }
                     }
                 }
@@ -1214,6 +1177,180 @@ pub fn start(
     stop_session(ctx);
 }
 
+/// Handles the response to a request from us, running the callback of its batch once all
+/// responses of the batch have arrived.
+pub fn process_server_response(
+    ctx: &mut Context,
+    server_id: ServerId,
+    output: Output,
+) -> ControlFlow<()> {
+    match output {
+        Output::Success(success) => {
+            if let Some((meta, method, batch_id, canceled)) =
+                ctx.response_waitlist.remove(&success.id)
+            {
+                if canceled {
+                    return ControlFlow::Continue(());
+                }
+                if !ctx.batches_to_complete.contains(&batch_id) {
+                    remove_outstanding_request(
+                        server_id,
+                        ctx,
+                        method,
+                        meta.buffile.clone(),
+                        meta.client.clone(),
+                        &success.id,
+                    );
+                }
+                return add_batch_response(ctx, meta, batch_id, server_id, success.result);
+            } else {
+                error!(ctx.to_editor(), "Id {:?} is not in waitlist!", success.id);
+            }
+        }
+        Output::Failure(failure) => {
+            if let Some(request) = ctx.response_waitlist.remove(&failure.id) {
+                let (meta, method, batch_id, canceled) = request;
+                if canceled {
+                    return ControlFlow::Continue(());
+                }
+                if ctx.batches_to_complete.contains(&batch_id) {
+                    debug!(
+                        ctx.to_editor(),
+                        "Error response from server {}: {:?}",
+                        &ctx.server(server_id).name,
+                        failure
+                    );
+                    return add_batch_response(
+                        ctx,
+                        meta,
+                        batch_id,
+                        server_id,
+                        serde_json::Value::Null,
+                    );
+                }
+                remove_outstanding_request(
+                    server_id,
+                    ctx,
+                    method,
+                    meta.buffile.clone(),
+                    meta.client.clone(),
+                    &failure.id,
+                );
+                if failure.error.code == ErrorCode::ServerError(CONTENT_MODIFIED) {
+                    debug!(
+                        ctx.to_editor(),
+                        "Error response from server {}: {:?}",
+                        &ctx.server(server_id).name,
+                        failure
+                    );
+                } else {
+                    error!(
+                        ctx.to_editor(),
+                        "Error response from server {}: {:?}",
+                        &ctx.server(server_id).name,
+                        failure
+                    );
+                }
+                if let Some((vals, callback)) = ctx.batches.remove(&batch_id) {
+                    if let Some(mut batch_seq) = ctx.batch_sizes.remove(&batch_id) {
+                        batch_seq.remove(&server_id);
+
+                        // We con only keep going if there are still other servers to respond.
+                        // Otherwise, skip the following block and handle failure.
+                        if !batch_seq.is_empty() {
+                            // Remove this failing language server from the batch, allowing
+                            // working ones to still be handled.
+                            let vals: Vec<_> =
+                                vals.into_iter().filter(|(s, _)| *s != server_id).collect();
+
+                            // Scenario: this failing server is holding back the response handling
+                            // for all other servers, which already responded successfully.
+                            let batch_size: usize = batch_seq.values().sum();
+                            if vals.len() >= batch_size {
+                                callback(ctx, meta, vals);
+                                if ctx.is_exiting {
+                                    return ControlFlow::Break(());
+                                }
+                            } else {
+                                // Re-insert the batch, as we have no business with it at the moment,
+                                // since not all servers have completely responded.
+                                ctx.batch_sizes.insert(batch_id, batch_seq);
+                                ctx.batches.insert(batch_id, (vals, callback));
+                            }
+
+                            return ControlFlow::Continue(());
+                        }
+                    }
+                }
+                match failure.error.code {
+                    code if code == ErrorCode::ServerError(CONTENT_MODIFIED)
+                        || method == request::CodeActionRequest::METHOD => {}
+                    // Type hierarchy requests go to all servers, see type_hierarchy_prepare.
+                    ErrorCode::MethodNotFound
+                        if method == request::TypeHierarchyPrepare::METHOD => {}
+                    code => {
+                        let msg = match code {
+                            ErrorCode::MethodNotFound => format!(
+                                "language server {} doesn't support method {}",
+                                &ctx.server(server_id).name,
+                                method
+                            ),
+                            _ if method == request::PrepareRenameRequest::METHOD => {
+                                format!("lsp-rename: cannot rename: {}", failure.error.message)
+                            }
+                            _ => format!(
+                                "language server {} error: {}",
+                                &ctx.server(server_id).name,
+                                editor_quote(&failure.error.message)
+                            ),
+                        };
+                        ctx.show_error(meta, msg);
+                    }
+                }
+            } else {
+                error!(
+                    ctx.to_editor(),
+                    "Error response from server {}: {:?}",
+                    &ctx.server(server_id).name,
+                    failure
+                );
+                error!(ctx.to_editor(), "Id {:?} is not in waitlist!", failure.id);
+            }
+        }
+    }
+    ControlFlow::Continue(())
+}
+
+/// Adds a response to its batch, and runs the callback once all responses have arrived.
+fn add_batch_response(
+    ctx: &mut Context,
+    meta: EditorMeta,
+    batch_id: BatchNumber,
+    server_id: ServerId,
+    result: serde_json::Value,
+) -> ControlFlow<()> {
+    let Some((mut vals, callback)) = ctx.batches.remove(&batch_id) else {
+        return ControlFlow::Continue(());
+    };
+    let Some(batch_seq) = ctx.batch_sizes.remove(&batch_id) else {
+        return ControlFlow::Continue(());
+    };
+    vals.push((server_id, result));
+    let batch_size: usize = batch_seq.values().sum();
+
+    if vals.len() >= batch_size {
+        ctx.batches_to_complete.remove(&batch_id);
+        callback(ctx, meta, vals);
+        if ctx.is_exiting {
+            return ControlFlow::Break(());
+        }
+    } else {
+        ctx.batch_sizes.insert(batch_id, batch_seq);
+        ctx.batches.insert(batch_id, (vals, callback));
+    }
+    ControlFlow::Continue(())
+}
+
 pub fn process_editor_request(ctx: &mut Context, mut request: EditorRequest) -> ControlFlow<()> {
     if let Some(flow) = route_request(ctx, &mut request.meta, &request.method) {
         return flow;
@@ -1304,13 +1441,7 @@ pub fn can_serve(
     requested_root_path: &RootPath,
 ) -> bool {
     let candidate = ctx.server(candidate_id);
-    let workspace_folder_support = candidate.capabilities.as_ref().is_some_and(|caps| {
-        caps.workspace.as_ref().is_some_and(|ws| {
-            ws.workspace_folders
-                .as_ref()
-                .is_some_and(|wsf| wsf.supported == Some(true))
-        })
-    });
+    let workspace_folder_support = workspace::supports_workspace_folder_changes(candidate);
     requested_server_name == &candidate.name
         && candidate.capabilities.is_some()
         && (candidate.roots.contains(requested_root_path)
@@ -1513,6 +1644,14 @@ fn route_request(
                 .push(root.clone());
             ctx.route_cache
                 .insert((server_name.clone(), root.clone()), server_id);
+            if workspace::supports_workspace_folder_changes(ctx.server(server_id)) {
+                workspace::did_change_workspace_folders(
+                    server_id,
+                    std::slice::from_ref(&root),
+                    &[],
+                    ctx,
+                );
+            }
             meta.servers.push(server_id);
             continue 'server;
         }
@@ -1755,6 +1894,9 @@ fn dispatch_editor_request(request: EditorRequest, ctx: &mut Context) -> Control
         request::CallHierarchyPrepare::METHOD => {
             call_hierarchy::call_hierarchy_prepare(meta, params.unbox(), ctx);
         }
+        request::TypeHierarchyPrepare::METHOD => {
+            type_hierarchy::type_hierarchy_prepare(meta, params.unbox(), ctx);
+        }
         request::CodeLensRequest::METHOD => {
             text_document_code_lens(meta, ctx);
         }
@@ -1816,9 +1958,39 @@ fn dispatch_editor_request(request: EditorRequest, ctx: &mut Context) -> Control
         request::DocumentHighlightRequest::METHOD => {
             highlight::text_document_highlight(meta, params.unbox(), ctx);
         }
+        request::DocumentColor::METHOD => {
+            document_color::document_colors(meta, ctx);
+        }
+        "kakoune/code-actions-fix-all" => {
+            code_action::code_actions_fix_all(meta, params.unbox(), ctx);
+        }
+        "kakoune/color-presentation/next" => {
+            document_color::color_presentation_next(meta, params.unbox(), ctx);
+        }
+        request::DocumentLinkRequest::METHOD => {
+            document_link::document_links_highlight(meta, ctx);
+        }
+        "kakoune/diagnostic-related-information" => {
+            diagnostics::diagnostic_related_information(meta, params.unbox(), ctx);
+        }
+        "kakoune/diagnostics-export" => {
+            diagnostics::diagnostics_export(meta, params.unbox(), ctx);
+        }
+        "kakoune/document-link/open" => {
+            document_link::document_link_open(meta, params.unbox(), ctx);
+        }
+        "kakoune/document-links" => {
+            document_link::document_links_list(meta, ctx);
+        }
+        request::LinkedEditingRange::METHOD => {
+            linked_editing_range::text_document_linked_editing_range(meta, params.unbox(), ctx);
+        }
         request::DocumentSymbolRequest::METHOD => {
             document_symbol::text_document_document_symbol(meta, params.unbox(), ctx);
         }
+        request::FoldingRangeRequest::METHOD => {
+            folding_range::text_document_folding_range(meta, params.unbox(), ctx);
+        }
         "kakoune/breadcrumbs" => {
             document_symbol::breadcrumbs(meta, params.unbox(), ctx);
         }
@@ -1845,6 +2017,9 @@ fn dispatch_editor_request(request: EditorRequest, ctx: &mut Context) -> Control
         "kakoune/inlay-hint-apply-selected" => {
             inlay_hints::inlay_hint_apply(meta, params.unbox(), ctx);
         }
+        "kakoune/next-or-previous-diagnostic" => {
+            diagnostics::next_or_prev_diagnostic(meta, params.unbox(), ctx);
+        }
         "kakoune/next-or-previous-symbol" => {
             document_symbol::next_or_prev_symbol(meta, params.unbox(), ctx);
         }
@@ -1857,9 +2032,18 @@ fn dispatch_editor_request(request: EditorRequest, ctx: &mut Context) -> Control
         "kakoune/textDocument/codeLens" => {
             code_lens::resolve_and_perform_code_lens(meta, params.unbox(), ctx);
         }
+        "kakoune/workspace-edit-preview/respond" => {
+            workspace::workspace_edit_preview_respond(meta, params.unbox(), ctx);
+        }
+        "kakoune/workspace-edit-preview/toggle" => {
+            workspace::workspace_edit_preview_toggle(meta, params.unbox(), ctx);
+        }
         request::Formatting::METHOD => {
             formatting::text_document_formatting(meta, response_fifo, params.unbox(), ctx);
         }
+        request::OnTypeFormatting::METHOD => {
+            on_type_formatting::text_document_on_type_formatting(meta, params.unbox(), ctx);
+        }
         request::RangeFormatting::METHOD => {
             range_formatting::text_document_range_formatting(
                 meta,
@@ -1871,9 +2055,15 @@ fn dispatch_editor_request(request: EditorRequest, ctx: &mut Context) -> Control
         request::WorkspaceSymbolRequest::METHOD => {
             workspace::workspace_symbol(meta, params.unbox(), ctx);
         }
+        request::PrepareRenameRequest::METHOD => {
+            rename::text_document_prepare_rename(meta, params.unbox(), ctx);
+        }
         request::Rename::METHOD => {
             rename::text_document_rename(meta, params.unbox(), ctx);
         }
+        request::WillRenameFiles::METHOD => {
+            workspace::rename_file(meta, params.unbox(), ctx);
+        }
         "textDocument/diagnostics" => {
             diagnostics::editor_diagnostics(meta, params.unbox(), ctx);
         }
@@ -1894,10 +2084,16 @@ fn dispatch_editor_request(request: EditorRequest, ctx: &mut Context) -> Control
         request::SemanticTokensFullRequest::METHOD => {
             semantic_tokens::tokens_request(meta, ctx);
         }
+        request::SemanticTokensRangeRequest::METHOD => {
+            semantic_tokens::tokens_range_request(meta, params.unbox(), ctx);
+        }
 
         request::InlayHintRequest::METHOD => {
             inlay_hints::inlay_hints(meta, params.unbox(), ctx);
         }
+        request::InlineValueRequest::METHOD => {
+            inline_values::inline_values(meta, params.unbox(), ctx);
+        }
 
         show_message::SHOW_MESSAGE_REQUEST_NEXT => {
             show_message::show_message_request_next(meta, ctx);
@@ -1974,7 +2170,7 @@ fn dispatch_server_request(
     let method: &str = &request.method;
     let result = match method {
         request::ApplyWorkspaceEdit::METHOD => {
-            workspace::apply_edit_from_server(meta, server_id, request.params, ctx)
+            return workspace::apply_edit_from_server(meta, server_id, request, ctx);
         }
         request::RegisterCapability::METHOD => {
             let params: RegistrationParams = request
@@ -1991,9 +2187,88 @@ fn dispatch_server_request(
                         )
                     }
                     notification::DidChangeWorkspaceFolders::METHOD => {
-                        // Since we only support one root path, we are never going to send
-                        // "workspace/didChangeWorkspaceFolders" anyway, so let's not issue a warning.
-                        continue;
+                        let capabilities = ctx
+                            .language_servers
+                            .get_mut(&server_id)
+                            .unwrap()
+                            .capabilities
+                            .as_mut()
+                            .unwrap();
+                        let workspace_folders = capabilities
+                            .workspace
+                            .get_or_insert_with(Default::default)
+                            .workspace_folders
+                            .get_or_insert_with(Default::default);
+                        workspace_folders.supported = Some(true);
+                        workspace_folders.change_notifications =
+                            Some(OneOf::Right(registration.id));
+                    }
+                    notification::DidCreateFiles::METHOD
+                    | notification::DidRenameFiles::METHOD
+                    | notification::DidDeleteFiles::METHOD
+                    | request::WillRenameFiles::METHOD => {
+                        let Some(options) = registration.register_options else {
+                            warn!(
+                                ctx.to_editor(),
+                                "file operation registration without options"
+                            );
+                            continue;
+                        };
+                        let file_operation_options: FileOperationRegistrationOptions =
+                            match serde_json::from_value(options) {
+                                Ok(v) => v,
+                                Err(e) => {
+                                    error!(
+                                        ctx.to_editor(),
+                                        "Failed to unmarshal file operation options: {}", e
+                                    );
+                                    continue;
+                                }
+                            };
+                        let file_operations = ctx
+                            .language_servers
+                            .get_mut(&server_id)
+                            .unwrap()
+                            .capabilities
+                            .as_mut()
+                            .unwrap()
+                            .workspace
+                            .get_or_insert_with(Default::default)
+                            .file_operations
+                            .get_or_insert_with(Default::default);
+                        let options = match registration.method.as_str() {
+                            notification::DidCreateFiles::METHOD => &mut file_operations.did_create,
+                            notification::DidRenameFiles::METHOD => &mut file_operations.did_rename,
+                            notification::DidDeleteFiles::METHOD => &mut file_operations.did_delete,
+                            _ => &mut file_operations.will_rename,
+                        };
+                        *options = Some(file_operation_options);
+                    }
+                    request::DocumentDiagnosticRequest::METHOD => {
+                        let Some(options) = registration.register_options else {
+                            warn!(ctx.to_editor(), "diagnostic registration without options");
+                            continue;
+                        };
+                        let diagnostic_options: DiagnosticRegistrationOptions =
+                            match serde_json::from_value(options) {
+                                Ok(v) => v,
+                                Err(e) => {
+                                    error!(
+                                        ctx.to_editor(),
+                                        "Failed to unmarshal diagnostic options: {}", e
+                                    );
+                                    continue;
+                                }
+                            };
+                        ctx.language_servers
+                            .get_mut(&server_id)
+                            .unwrap()
+                            .capabilities
+                            .as_mut()
+                            .unwrap()
+                            .diagnostic_provider = Some(
+                            DiagnosticServerCapabilities::RegistrationOptions(diagnostic_options),
+                        );
                     }
                     "textDocument/semanticTokens" => {
                         let Some(options) = registration.register_options else {
@@ -2051,6 +2326,9 @@ fn dispatch_server_request(
         request::WorkspaceConfiguration::METHOD => {
             workspace::configuration(meta, request.params, server_id, ctx)
         }
+        request::ShowDocument::METHOD => {
+            show_message::show_document(server_id, request.params, ctx)
+        }
         request::ShowMessageRequest::METHOD => {
             return show_message::show_message_request(meta, server_id, request, ctx);
         }
@@ -2068,6 +2346,17 @@ fn dispatch_server_request(
             );
             Ok(serde_json::Value::Null)
         }
+        request::InlineValueRefreshRequest::METHOD => {
+            ctx.exec(
+                meta,
+                "evaluate-commands -buffer * unset-option buffer lsp_inline_values_timestamp",
+            );
+            Ok(serde_json::Value::Null)
+        }
+        request::WorkspaceDiagnosticRefresh::METHOD => {
+            diagnostics::workspace_diagnostic_refresh(server_id, meta, ctx);
+            Ok(serde_json::Value::Null)
+        }
         request::SemanticTokensRefresh::METHOD => {
             ctx.exec(
                 meta,
@@ -1,4 +1,8 @@
+use crate::capabilities::{
+    attempt_server_capability, CAPABILITY_PULL_DIAGNOSTICS, CAPABILITY_WORKSPACE_PULL_DIAGNOSTICS,
+};
 use crate::context::*;
+use crate::language_features::goto::edit_at_range;
 use crate::markup::escape_kakoune_markup;
 use crate::position::*;
 use crate::types::*;
@@ -7,14 +11,45 @@ use crate::util::*;
 use itertools::EitherOrBoth;
 use itertools::Itertools;
 use jsonrpc_core::Params;
+use lsp_types::request::{DocumentDiagnosticRequest, WorkspaceDiagnosticRequest};
 use lsp_types::*;
+use ropey::Rope;
+use serde_json::json;
+use std::cmp::min;
 use std::collections::HashMap;
 use std::fmt::Write as _;
+use std::fs;
+use std::os::unix::fs::FileTypeExt;
+use std::thread;
 
 pub fn publish_diagnostics(server_id: ServerId, params: Params, ctx: &mut Context) {
     let params: PublishDiagnosticsParams = params.parse().expect("Failed to parse params");
     let buffile = uri_to_file_path(&params.uri);
     let buffile = buffile.to_str().unwrap();
+    // Diagnostics for an older version would be shown at the wrong positions, so drop them.
+    // The buffer's diagnostics were already cleared when it changed.
+    if let (Some(version), Some(document)) = (params.version, ctx.documents.get(buffile)) {
+        if version < document.version {
+            debug!(
+                ctx.to_editor(),
+                "Dropping diagnostics for {} version {}, document is at version {}",
+                buffile,
+                version,
+                document.version
+            );
+            return;
+        }
+    }
+    update_diagnostics(server_id, buffile, params.diagnostics, ctx);
+}
+
+/// Replace the diagnostics of one server for the given file and render them.
+fn update_diagnostics(
+    server_id: ServerId,
+    buffile: &str,
+    server_diagnostics: Vec<Diagnostic>,
+    ctx: &mut Context,
+) {
     let mut diagnostics: Vec<_> = ctx
         .diagnostics
         .remove(buffile)
@@ -22,12 +57,11 @@ pub fn publish_diagnostics(server_id: ServerId, params: Params, ctx: &mut Contex
         .into_iter()
         .filter(|(id, _)| id != &server_id)
         .collect();
-    let params: Vec<_> = params
-        .diagnostics
+    let server_diagnostics: Vec<_> = server_diagnostics
         .into_iter()
         .map(|d| (server_id, d))
         .collect();
-    diagnostics.extend(params);
+    diagnostics.extend(server_diagnostics);
     ctx.diagnostics.insert(buffile.to_string(), diagnostics);
     let document = ctx.documents.get(buffile);
     if document.is_none() {
@@ -189,6 +223,215 @@ pub fn publish_diagnostics(server_id: ServerId, params: Params, ctx: &mut Contex
     ctx.exec(EditorMeta::default(), command);
 }
 
+/// Pull diagnostics for the current buffer from servers that support
+/// textDocument/diagnostic.
+pub fn text_document_diagnostic(meta: EditorMeta, ctx: &mut Context) {
+    let eligible_servers: Vec<_> = ctx
+        .servers(&meta)
+        .filter(|srv| attempt_server_capability(ctx, *srv, &meta, CAPABILITY_PULL_DIAGNOSTICS))
+        .collect();
+    if eligible_servers.is_empty() {
+        return;
+    }
+
+    let uri = file_path_to_uri(&meta.buffile);
+    let req_params = eligible_servers
+        .into_iter()
+        .map(|(server_id, server)| {
+            let previous_result_id = ctx
+                .pulled_diagnostics
+                .get(&(server_id, meta.buffile.clone()))
+                .and_then(|(result_id, _)| result_id.clone());
+            (
+                server_id,
+                vec![DocumentDiagnosticParams {
+                    text_document: TextDocumentIdentifier { uri: uri.clone() },
+                    identifier: diagnostic_options(server)
+                        .and_then(|options| options.identifier.clone()),
+                    previous_result_id,
+                    work_done_progress_params: Default::default(),
+                    partial_result_params: Default::default(),
+                }],
+            )
+        })
+        .collect();
+    ctx.call::<DocumentDiagnosticRequest, _>(
+        meta,
+        RequestParams::Each(req_params),
+        move |ctx, meta, results| {
+            for (server_id, result) in results {
+                let related_documents = match result {
+                    DocumentDiagnosticReportResult::Report(DocumentDiagnosticReport::Full(
+                        report,
+                    )) => {
+                        update_pulled_diagnostics(
+                            server_id,
+                            &meta.buffile,
+                            DocumentDiagnosticReportKind::Full(
+                                report.full_document_diagnostic_report,
+                            ),
+                            ctx,
+                        );
+                        report.related_documents
+                    }
+                    DocumentDiagnosticReportResult::Report(
+                        DocumentDiagnosticReport::Unchanged(report),
+                    ) => {
+                        update_pulled_diagnostics(
+                            server_id,
+                            &meta.buffile,
+                            DocumentDiagnosticReportKind::Unchanged(
+                                report.unchanged_document_diagnostic_report,
+                            ),
+                            ctx,
+                        );
+                        report.related_documents
+                    }
+                    DocumentDiagnosticReportResult::Partial(partial) => partial.related_documents,
+                };
+                for (uri, report) in related_documents.unwrap_or_default() {
+                    let buffile = uri_to_file_path(&uri);
+                    update_pulled_diagnostics(server_id, buffile.to_str().unwrap(), report, ctx);
+                }
+            }
+        },
+    );
+}
+
+/// Pull diagnostics for the whole workspace, then run the callback.
+/// The callback is not run if no server supports workspace diagnostics.
+pub fn workspace_diagnostic(
+    meta: EditorMeta,
+    ctx: &mut Context,
+    callback: impl FnOnce(&mut Context, EditorMeta) + 'static,
+) {
+    let eligible_servers: Vec<_> = ctx
+        .servers(&meta)
+        .filter(|srv| {
+            attempt_server_capability(ctx, *srv, &meta, CAPABILITY_WORKSPACE_PULL_DIAGNOSTICS)
+        })
+        .collect();
+    if eligible_servers.is_empty() {
+        return;
+    }
+
+    let req_params = eligible_servers
+        .into_iter()
+        .map(|(server_id, server)| {
+            let previous_result_ids = ctx
+                .pulled_diagnostics
+                .iter()
+                .filter(|((id, _), _)| *id == server_id)
+                .filter_map(|((_, buffile), (result_id, _))| {
+                    Some(PreviousResultId {
+                        uri: file_path_to_uri(buffile),
+                        value: result_id.clone()?,
+                    })
+                })
+                .collect();
+            (
+                server_id,
+                vec![WorkspaceDiagnosticParams {
+                    identifier: diagnostic_options(server)
+                        .and_then(|options| options.identifier.clone()),
+                    previous_result_ids,
+                    work_done_progress_params: Default::default(),
+                    partial_result_params: Default::default(),
+                }],
+            )
+        })
+        .collect();
+    ctx.call::<WorkspaceDiagnosticRequest, _>(
+        meta,
+        RequestParams::Each(req_params),
+        move |ctx, meta, results| {
+            for (server_id, result) in results {
+                let items = match result {
+                    WorkspaceDiagnosticReportResult::Report(report) => report.items,
+                    WorkspaceDiagnosticReportResult::Partial(partial) => partial.items,
+                };
+                for item in items {
+                    let (uri, report) = match item {
+                        WorkspaceDocumentDiagnosticReport::Full(report) => (
+                            report.uri,
+                            DocumentDiagnosticReportKind::Full(
+                                report.full_document_diagnostic_report,
+                            ),
+                        ),
+                        WorkspaceDocumentDiagnosticReport::Unchanged(report) => (
+                            report.uri,
+                            DocumentDiagnosticReportKind::Unchanged(
+                                report.unchanged_document_diagnostic_report,
+                            ),
+                        ),
+                    };
+                    let buffile = uri_to_file_path(&uri);
+                    update_pulled_diagnostics(server_id, buffile.to_str().unwrap(), report, ctx);
+                }
+            }
+            callback(ctx, meta)
+        },
+    );
+}
+
+/// Handle workspace/diagnostic/refresh by pulling diagnostics again for all open documents
+/// we have pulled diagnostics for before.
+pub fn workspace_diagnostic_refresh(server_id: ServerId, meta: EditorMeta, ctx: &mut Context) {
+    let buffiles: Vec<_> = ctx
+        .pulled_diagnostics
+        .keys()
+        .filter(|(id, buffile)| *id == server_id && ctx.documents.contains_key(buffile))
+        .map(|(_, buffile)| buffile.clone())
+        .collect();
+    for buffile in buffiles {
+        let meta = EditorMeta {
+            buffile,
+            hook: true,
+            servers: vec![server_id],
+            ..meta.clone()
+        };
+        text_document_diagnostic(meta, ctx);
+    }
+}
+
+fn update_pulled_diagnostics(
+    server_id: ServerId,
+    buffile: &str,
+    report: DocumentDiagnosticReportKind,
+    ctx: &mut Context,
+) {
+    let key = (server_id, buffile.to_string());
+    let items = match report {
+        DocumentDiagnosticReportKind::Full(report) => {
+            ctx.pulled_diagnostics
+                .insert(key, (report.result_id, report.items.clone()));
+            report.items
+        }
+        DocumentDiagnosticReportKind::Unchanged(report) => match ctx.pulled_diagnostics.get(&key) {
+            Some((result_id, items)) if result_id.as_ref() == Some(&report.result_id) => {
+                items.clone()
+            }
+            _ => {
+                warn!(
+                    ctx.to_editor(),
+                    "Unchanged diagnostic report for unknown result id {}", report.result_id
+                );
+                return;
+            }
+        },
+    };
+    update_diagnostics(server_id, buffile, items, ctx);
+}
+
+fn diagnostic_options(server: &ServerSettings) -> Option<&DiagnosticOptions> {
+    match server.capabilities.as_ref()?.diagnostic_provider.as_ref()? {
+        DiagnosticServerCapabilities::Options(options) => Some(options),
+        DiagnosticServerCapabilities::RegistrationOptions(options) => {
+            Some(&options.diagnostic_options)
+        }
+    }
+}
+
 pub fn gather_line_flags(ctx: &Context, buffile: &str) -> (String, u32, u32, u32, u32) {
     let diagnostics = ctx.diagnostics.get(buffile);
     let mut error_count: u32 = 0;
@@ -315,6 +558,15 @@ pub fn diagnostic_text(
 }
 
 pub fn editor_diagnostics(meta: EditorMeta, params: PositionParams, ctx: &mut Context) {
+    // Servers may fail workspace/diagnostic or hold it open, so show the diagnostics we have
+    // right away and refresh the buffer once the pull returns.
+    show_diagnostics_buffer(meta.clone(), params, ctx);
+    workspace_diagnostic(meta, ctx, move |ctx, meta| {
+        show_diagnostics_buffer(meta, params, ctx)
+    });
+}
+
+fn show_diagnostics_buffer(meta: EditorMeta, params: PositionParams, ctx: &mut Context) {
     let mut goto_buffer_line = None;
     let mut line = 1;
     let content = ctx
@@ -374,6 +626,445 @@ pub fn editor_diagnostics(meta: EditorMeta, params: PositionParams, ctx: &mut Co
     ctx.exec(meta, command);
 }
 
+/// Restricts the diagnostics visited by lsp-next-diagnostic and lsp-previous-diagnostic, or
+/// fixed by lsp-code-actions-fix-all.
+#[derive(Debug, Default, PartialEq)]
+pub struct DiagnosticFilter {
+    min_severity: Option<DiagnosticSeverity>,
+    server: Option<String>,
+    source: Option<String>,
+    code: Option<String>,
+    /// Also visit files that are not open in the editor.
+    pub project: bool,
+}
+
+impl DiagnosticFilter {
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        let mut filter = DiagnosticFilter::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .cloned()
+                    .ok_or_else(|| format!("missing argument to {}", arg))
+            };
+            match arg.as_str() {
+                "-severity" => {
+                    filter.min_severity = Some(match value()?.as_str() {
+                        "error" => DiagnosticSeverity::ERROR,
+                        "warning" => DiagnosticSeverity::WARNING,
+                        "info" => DiagnosticSeverity::INFORMATION,
+                        "hint" => DiagnosticSeverity::HINT,
+                        severity => return Err(format!("invalid severity: {}", severity)),
+                    })
+                }
+                "-server" => filter.server = Some(value()?),
+                "-source" => filter.source = Some(value()?),
+                "-code" => filter.code = Some(value()?),
+                "-project" => filter.project = true,
+                _ => return Err(format!("invalid argument: {}", arg)),
+            }
+        }
+        Ok(filter)
+    }
+
+    pub fn matches(&self, server: &ServerSettings, diagnostic: &Diagnostic) -> bool {
+        let severity = diagnostic.severity.unwrap_or(DiagnosticSeverity::WARNING);
+        // Smaller == higher severity
+        self.min_severity
+            .map_or(true, |min_severity| severity <= min_severity)
+            && self
+                .server
+                .as_ref()
+                .map_or(true, |name| *name == server.name)
+            && self
+                .source
+                .as_ref()
+                .map_or(true, |source| diagnostic.source.as_ref() == Some(source))
+            && self.code.as_ref().map_or(true, |code| {
+                diagnostic
+                    .code
+                    .as_ref()
+                    .is_some_and(|diagnostic_code| match diagnostic_code {
+                        NumberOrString::Number(number) => number.to_string() == *code,
+                        NumberOrString::String(string) => string == code,
+                    })
+            })
+    }
+}
+
+/// Jumps to the next or previous matching diagnostic, continuing in the next or previous file
+/// with diagnostics, and wrapping around at the last one.
+pub fn next_or_prev_diagnostic(
+    meta: EditorMeta,
+    params: NextOrPrevDiagnosticParams,
+    ctx: &mut Context,
+) {
+    let command_name = if params.search_next {
+        "lsp-next-diagnostic"
+    } else {
+        "lsp-previous-diagnostic"
+    };
+    let filter = match DiagnosticFilter::parse(&params.filters) {
+        Ok(filter) => filter,
+        Err(err) => {
+            ctx.show_error(meta, format!("{}: {}", command_name, err));
+            return;
+        }
+    };
+
+    let mut locations = vec![];
+    for (filename, diagnostics) in &ctx.diagnostics {
+        if !(filter.project || *filename == meta.buffile || ctx.documents.contains_key(filename)) {
+            continue;
+        }
+        let mut diagnostics = diagnostics
+            .iter()
+            .filter(|(server_id, diagnostic)| filter.matches(ctx.server(*server_id), diagnostic))
+            .peekable();
+        if diagnostics.peek().is_none() {
+            continue;
+        }
+        let Some(text) = get_file_contents(filename, ctx) else {
+            continue;
+        };
+        for (server_id, diagnostic) in diagnostics {
+            let server = ctx.server(*server_id);
+            let range = lsp_range_to_kakoune(&diagnostic.range, &text, server.offset_encoding);
+            locations.push((filename.as_str(), range));
+        }
+    }
+    locations.sort_by_key(|(filename, range)| (*filename, range.start));
+
+    let cursor = (meta.buffile.as_str(), params.position);
+    let target = if params.search_next {
+        locations
+            .iter()
+            .find(|(filename, range)| (*filename, range.start) > cursor)
+            .or_else(|| locations.first())
+    } else {
+        locations
+            .iter()
+            .rev()
+            .find(|(filename, range)| (*filename, range.start) < cursor)
+            .or_else(|| locations.last())
+    };
+    let Some((filename, range)) = target else {
+        ctx.show_error(meta, format!("{}: no diagnostics found", command_name));
+        return;
+    };
+    let command = edit_at_range(filename, *range, true);
+    ctx.exec(meta, command);
+}
+
+/// Lists the related locations of the diagnostics at the cursor, like where a conflicting
+/// definition is, and optionally highlights them in their buffers.
+pub fn diagnostic_related_information(
+    meta: EditorMeta,
+    params: DiagnosticRelatedInformationParams,
+    ctx: &mut Context,
+) {
+    let Some(document) = ctx.documents.get(&meta.buffile) else {
+        return;
+    };
+    let diagnostics: Vec<_> = ctx
+        .diagnostics
+        .get(&meta.buffile)
+        .into_iter()
+        .flatten()
+        .filter_map(|(server_id, diagnostic)| {
+            let server = ctx.server(*server_id);
+            let range =
+                lsp_range_to_kakoune(&diagnostic.range, &document.text, server.offset_encoding);
+            (range.start <= params.position && params.position <= range.end)
+                .then_some((server, diagnostic, range))
+        })
+        .collect();
+    if diagnostics.is_empty() {
+        ctx.show_error(
+            meta,
+            "lsp-diagnostic-related-information: no diagnostic at cursor",
+        );
+        return;
+    }
+
+    let mut content = vec![];
+    let mut highlights: HashMap<&str, Vec<String>> = HashMap::new();
+    for (server, diagnostic, range) in diagnostics {
+        let Some(infos) = diagnostic
+            .related_information
+            .as_ref()
+            .filter(|infos| !infos.is_empty())
+        else {
+            continue;
+        };
+        content.push(format!(
+            "{}:{}:{}: {}",
+            short_file_path(&meta.buffile, ctx.main_root(&meta)),
+            range.start.line,
+            range.start.column,
+            diagnostic_text(ctx.to_editor(), diagnostic, None, false),
+        ));
+        for info in infos {
+            let filename = uri_to_file_path(&info.location.uri);
+            let filename = filename.to_str().unwrap();
+            let p = get_kakoune_position_with_fallback(
+                server,
+                filename,
+                info.location.range.start,
+                ctx,
+            );
+            content.push(format!(
+                "    {}:{}:{}: {}",
+                short_file_path(filename, ctx.main_root(&meta)),
+                p.line,
+                p.column,
+                info.message.replace('\n', "␊"),
+            ));
+            // Only open buffers can be highlighted.
+            if let Some((buffile, document)) = ctx.documents.get_key_value(filename) {
+                let range = lsp_range_to_kakoune(
+                    &info.location.range,
+                    &document.text,
+                    server.offset_encoding,
+                );
+                highlights
+                    .entry(buffile)
+                    .or_default()
+                    .push(editor_quote(&format!(
+                        "{}|DiagnosticRelatedInformation",
+                        ForwardKakouneRange(range)
+                    )));
+            }
+        }
+    }
+    if content.is_empty() {
+        ctx.show_error(
+            meta,
+            "lsp-diagnostic-related-information: diagnostic at cursor has no related information",
+        );
+        return;
+    }
+
+    let mut command = format!(
+        "lsp-show-related-information {} {}",
+        editor_quote(ctx.main_root(&meta)),
+        editor_quote(&content.join("\n")),
+    );
+    if params.highlight {
+        command.push_str("\nlsp-diagnostic-related-information-clear");
+        for (buffile, ranges) in highlights {
+            let version = ctx.documents[buffile].version;
+            let _ = write!(
+                command,
+                "\nevaluate-commands -buffer {} -verbatim -- set-option buffer lsp_related_information {} {}",
+                editor_quote(buffile),
+                version,
+                ranges.join(" ")
+            );
+        }
+    }
+    ctx.exec(meta, command);
+}
+
+fn severity_name(severity: Option<DiagnosticSeverity>) -> &'static str {
+    match severity {
+        Some(DiagnosticSeverity::ERROR) => "error",
+        Some(DiagnosticSeverity::HINT) => "hint",
+        Some(DiagnosticSeverity::INFORMATION) => "info",
+        _ => "warning",
+    }
+}
+
+fn code_text(code: &NumberOrString) -> String {
+    match code {
+        NumberOrString::Number(code) => code.to_string(),
+        NumberOrString::String(code) => code.clone(),
+    }
+}
+
+/// Converts an LSP position to the 1-based line and UTF-16 column used by SARIF.
+fn sarif_position(
+    position: &Position,
+    text: Option<&Rope>,
+    offset_encoding: OffsetEncoding,
+) -> (u32, u32) {
+    let line = text.and_then(|text| text.get_line(position.line as usize));
+    let column = match (offset_encoding, line) {
+        (OffsetEncoding::Utf8, Some(line)) => {
+            let char_idx = line.byte_to_char(min(position.character as usize, line.len_bytes()));
+            line.slice(..char_idx)
+                .chars()
+                .map(char::len_utf16)
+                .sum::<usize>() as u32
+        }
+        _ => position.character,
+    };
+    (position.line + 1, column + 1)
+}
+
+fn diagnostics_to_sarif(
+    diagnostics: &[(&str, &ServerSettings, &Diagnostic, Option<Rope>)],
+) -> serde_json::Value {
+    let runs = diagnostics
+        .iter()
+        .into_group_map_by(|(_, server, _, _)| server.name.as_str())
+        .into_iter()
+        .sorted_by_key(|(server_name, _)| *server_name)
+        .map(|(server_name, diagnostics)| {
+            let results = diagnostics
+                .into_iter()
+                .map(|(filename, server, diagnostic, text)| {
+                    let (start_line, start_column) = sarif_position(
+                        &diagnostic.range.start,
+                        text.as_ref(),
+                        server.offset_encoding,
+                    );
+                    let (end_line, end_column) = sarif_position(
+                        &diagnostic.range.end,
+                        text.as_ref(),
+                        server.offset_encoding,
+                    );
+                    let mut result = json!({
+                        "level": match diagnostic.severity {
+                            Some(DiagnosticSeverity::ERROR) => "error",
+                            Some(DiagnosticSeverity::WARNING) | None => "warning",
+                            _ => "note",
+                        },
+                        "message": { "text": diagnostic.message },
+                        "locations": [{
+                            "physicalLocation": {
+                                "artifactLocation": { "uri": file_path_to_uri(filename).as_str() },
+                                "region": {
+                                    "startLine": start_line,
+                                    "startColumn": start_column,
+                                    "endLine": end_line,
+                                    "endColumn": end_column,
+                                }
+                            }
+                        }],
+                    });
+                    if let Some(code) = &diagnostic.code {
+                        result["ruleId"] = json!(code_text(code));
+                    }
+                    result
+                })
+                .collect::<Vec<_>>();
+            json!({
+                "tool": { "driver": { "name": server_name } },
+                "columnKind": "utf16CodeUnits",
+                "results": results,
+            })
+        })
+        .collect::<Vec<_>>();
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": runs,
+    })
+}
+
+/// Writes the diagnostics of the current buffer or of all files to a file or FIFO, for
+/// comparing them with the output of other tools.
+pub fn diagnostics_export(meta: EditorMeta, params: DiagnosticsExportParams, ctx: &mut Context) {
+    let mut diagnostics = vec![];
+    for (filename, file_diagnostics) in &ctx.diagnostics {
+        if !params.session && *filename != meta.buffile {
+            continue;
+        }
+        if file_diagnostics.is_empty() {
+            continue;
+        }
+        let text = get_file_contents(filename, ctx);
+        for (server_id, diagnostic) in file_diagnostics {
+            let server = ctx.server(*server_id);
+            diagnostics.push((filename.as_str(), server, diagnostic, text.clone()));
+        }
+    }
+    diagnostics.sort_by_key(|(filename, server, diagnostic, _)| {
+        (*filename, diagnostic.range.start, server.name.as_str())
+    });
+
+    let content = match params.format {
+        DiagnosticsExportFormat::Json => {
+            let diagnostics = diagnostics
+                .iter()
+                .map(|(filename, server, diagnostic, _)| {
+                    json!({
+                        "file": filename,
+                        "server": server.name,
+                        "diagnostic": diagnostic,
+                    })
+                })
+                .collect::<Vec<_>>();
+            serde_json::to_string_pretty(&diagnostics).unwrap() + "\n"
+        }
+        DiagnosticsExportFormat::Sarif => {
+            serde_json::to_string_pretty(&diagnostics_to_sarif(&diagnostics)).unwrap() + "\n"
+        }
+        DiagnosticsExportFormat::Quickfix => diagnostics
+            .iter()
+            .map(|(filename, server, diagnostic, text)| {
+                let p = match text {
+                    Some(text) => lsp_position_to_kakoune(
+                        &diagnostic.range.start,
+                        text,
+                        server.offset_encoding,
+                    ),
+                    None => KakounePosition {
+                        line: diagnostic.range.start.line + 1,
+                        column: diagnostic.range.start.character + 1,
+                    },
+                };
+                format!(
+                    "{}:{}:{}: {}: {}\n",
+                    short_file_path(filename, ctx.main_root(&meta)),
+                    p.line,
+                    p.column,
+                    severity_name(diagnostic.severity),
+                    diagnostic.message.trim().lines().join(" "),
+                )
+            })
+            .join(""),
+    };
+    let count = diagnostics.len();
+
+    // Opening a FIFO blocks until there is a reader, so write to it in the background.
+    let is_fifo = fs::metadata(&params.path).is_ok_and(|metadata| metadata.file_type().is_fifo());
+    if is_fifo {
+        let to_editor = ctx.to_editor().clone();
+        let path = params.path;
+        thread::spawn(move || {
+            if let Err(err) = fs::write(&path, content) {
+                error!(
+                    &to_editor,
+                    "Failed to write diagnostics to {}: {}", path, err
+                );
+            }
+        });
+        return;
+    }
+    match fs::write(&params.path, content) {
+        Ok(()) => ctx.exec(
+            meta,
+            format!(
+                "echo -- {}",
+                editor_quote(&format!(
+                    "lsp-diagnostics-export: wrote {} diagnostics to {}",
+                    count, params.path
+                ))
+            ),
+        ),
+        Err(err) => ctx.show_error(
+            meta,
+            format!(
+                "lsp-diagnostics-export: failed to write {}: {}",
+                params.path, err
+            ),
+        ),
+    }
+}
+
 pub fn format_related_information(
     d: &Diagnostic,
     server: &ServerSettings,
@@ -413,3 +1104,80 @@ pub fn format_related_information(
                     .join("\n")
         })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor_transport::mock_to_editor;
+
+    #[test]
+    fn publish_diagnostics_ignores_older_version() {
+        let mut ctx = Context::new(
+            SessionId("session".to_string()),
+            mock_to_editor(),
+            Config::default(),
+        );
+        let buffile = "/src/main.rs";
+        ctx.documents.insert(
+            buffile.to_string(),
+            Document {
+                version: 3,
+                text: Rope::from_str("fn main() {}\n"),
+            },
+        );
+        let diagnostic = |message: &str| Diagnostic {
+            message: message.to_string(),
+            ..Default::default()
+        };
+        ctx.diagnostics
+            .insert(buffile.to_string(), vec![(0, diagnostic("current"))]);
+        let params = PublishDiagnosticsParams {
+            uri: file_path_to_uri(buffile),
+            diagnostics: vec![diagnostic("stale")],
+            version: Some(2),
+        };
+        let params = match serde_json::to_value(params).unwrap() {
+            serde_json::Value::Object(map) => Params::Map(map),
+            _ => unreachable!(),
+        };
+        publish_diagnostics(0, params, &mut ctx);
+        assert_eq!(ctx.diagnostics[buffile], vec![(0, diagnostic("current"))]);
+    }
+
+    #[test]
+    fn diagnostic_filter_parse() {
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            DiagnosticFilter::parse(&args(&[
+                "-severity",
+                "warning",
+                "-source",
+                "clippy",
+                "-project"
+            ])),
+            Ok(DiagnosticFilter {
+                min_severity: Some(DiagnosticSeverity::WARNING),
+                source: Some("clippy".to_string()),
+                project: true,
+                ..Default::default()
+            })
+        );
+        assert!(DiagnosticFilter::parse(&args(&["-code"])).is_err());
+        assert!(DiagnosticFilter::parse(&args(&["-severity", "fatal"])).is_err());
+    }
+
+    #[test]
+    fn sarif_position_counts_utf16_code_units() {
+        let text = Rope::from_str("let 😀 = x;\n");
+        // The byte offset of "=" in the UTF-8 encoding.
+        let position = Position::new(0, 9);
+        assert_eq!(
+            sarif_position(&position, Some(&text), OffsetEncoding::Utf8),
+            (1, 8)
+        );
+        assert_eq!(
+            sarif_position(&Position::new(0, 7), Some(&text), OffsetEncoding::Utf16),
+            (1, 8)
+        );
+    }
+}
@@ -98,7 +98,7 @@ fn request_call_hierarchy(
     }
 }
 
-fn format_location(
+pub fn format_location(
     server_id: ServerId,
     meta: &EditorMeta,
     ctx: &mut Context,
@@ -107,11 +107,31 @@ fn format_location(
     prefix: &str,
     suffix: &str,
 ) -> String {
-    let server = ctx.server(server_id);
+    let offset_encoding = ctx.server(server_id).offset_encoding;
+    let root = ctx.main_root(meta);
+    format_location_line(ctx, root, offset_encoding, uri, position, prefix, suffix)
+}
+
+/// The position is converted using the contents of the location's file, which is not
+/// necessarily the current buffer.
+fn format_location_line(
+    ctx: &Context,
+    root: &str,
+    offset_encoding: OffsetEncoding,
+    uri: &Uri,
+    position: Position,
+    prefix: &str,
+    suffix: &str,
+) -> String {
     let path = uri_to_file_path(uri);
     let path = path.to_str().unwrap();
-    let filename = short_file_path(path, ctx.main_root(meta));
-    let position = get_kakoune_position_with_fallback(server, &meta.buffile, position, ctx);
+    let filename = short_file_path(path, root);
+    let position = get_file_contents(path, ctx)
+        .map(|text| lsp_position_to_kakoune(&position, &text, offset_encoding))
+        .unwrap_or(KakounePosition {
+            line: position.line + 1,
+            column: position.character + 1,
+        });
     format!(
         "{}{}:{}:{}: {}\n",
         prefix, filename, position.line, position.column, suffix,
@@ -234,3 +254,44 @@ fn format_call_hierarchy_calls<'a>(
     );
     ctx.exec(meta, command);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor_transport::mock_to_editor;
+    use ropey::Rope;
+
+    #[test]
+    fn format_location_line_uses_location_file() {
+        let mut ctx = Context::new(
+            SessionId("session".to_string()),
+            mock_to_editor(),
+            Config::default(),
+        );
+        for (path, text) in [
+            ("/src/main.rs", "fn main() {}\n"),
+            ("/src/lib.rs", "§𝕏 fn f() {}\n"),
+        ] {
+            ctx.documents.insert(
+                path.to_string(),
+                Document {
+                    version: 1,
+                    text: Rope::from_str(text),
+                },
+            );
+        }
+        let line = format_location_line(
+            &ctx,
+            "/src",
+            OffsetEncoding::Utf16,
+            &file_path_to_uri("/src/lib.rs"),
+            Position {
+                line: 0,
+                character: 7,
+            },
+            "",
+            "f",
+        );
+        assert_eq!(line, "lib.rs:1:11: f\n");
+    }
+}
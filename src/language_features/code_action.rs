@@ -7,15 +7,19 @@ use crate::capabilities::attempt_server_capability;
 use crate::capabilities::CAPABILITY_CODE_ACTIONS;
 use crate::capabilities::CAPABILITY_CODE_ACTIONS_RESOLVE;
 use crate::context::*;
+use crate::diagnostics::DiagnosticFilter;
 use crate::position::*;
 use crate::types::*;
 use crate::util::*;
 use crate::wcwidth;
+use crate::workspace::apply_edit_then;
 use indoc::formatdoc;
 use itertools::Itertools;
 use lazy_static::lazy_static;
 use lsp_types::request::*;
 use lsp_types::*;
+use ropey::Rope;
+use std::fmt::Write as _;
 
 pub fn text_document_code_action(
     meta: EditorMeta,
@@ -344,6 +348,372 @@ fn editor_code_actions(
     ctx.exec(meta, command);
 }
 
+/// Requests quick fixes for each matching diagnostic in the buffer and applies the preferred
+/// ones in a single edit, skipping fixes that overlap with one that was already picked.
+pub fn code_actions_fix_all(meta: EditorMeta, params: CodeActionsFixAllParams, ctx: &mut Context) {
+    let filter = match DiagnosticFilter::parse(&params.filters) {
+        Ok(filter) if filter.project => {
+            ctx.show_error(meta, "lsp-code-actions-fix-all: -project is not supported");
+            return;
+        }
+        Ok(filter) => filter,
+        Err(err) => {
+            ctx.show_error(meta, format!("lsp-code-actions-fix-all: {}", err));
+            return;
+        }
+    };
+    let Some(document) = ctx.documents.get(&meta.buffile) else {
+        return;
+    };
+    let version = document.version;
+
+    let mut req_params: HashMap<ServerId, Vec<CodeActionParams>> = HashMap::new();
+    for (server_id, diagnostic) in ctx.diagnostics.get(&meta.buffile).into_iter().flatten() {
+        let server = ctx.server(*server_id);
+        if !filter.matches(server, diagnostic)
+            || !attempt_server_capability(ctx, (*server_id, server), &meta, CAPABILITY_CODE_ACTIONS)
+        {
+            continue;
+        }
+        req_params
+            .entry(*server_id)
+            .or_default()
+            .push(CodeActionParams {
+                text_document: TextDocumentIdentifier {
+                    uri: file_path_to_uri(&meta.buffile),
+                },
+                range: diagnostic.range,
+                context: CodeActionContext {
+                    diagnostics: vec![diagnostic.clone()],
+                    only: Some(vec![CodeActionKind::QUICKFIX]),
+                    trigger_kind: Some(CodeActionTriggerKind::INVOKED),
+                },
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+            });
+    }
+    if req_params.is_empty() {
+        ctx.show_error(meta, "lsp-code-actions-fix-all: no matching diagnostics");
+        return;
+    }
+    // There is one request per diagnostic, none of them may be canceled as a duplicate.
+    ctx.call_to_completion::<CodeActionRequest, _>(
+        meta,
+        RequestParams::Each(req_params),
+        move |ctx, meta, results| editor_code_actions_fix_all(meta, version, results, ctx),
+    );
+}
+
+/// Returns the text edits of a workspace edit with their change annotations, or None if it also
+/// creates, renames or deletes files.
+fn workspace_edit_text_edits(
+    edit: &WorkspaceEdit,
+) -> Option<Vec<(Uri, TextEdit, Option<ChangeAnnotation>)>> {
+    let annotation = |id: &ChangeAnnotationIdentifier| {
+        edit.change_annotations
+            .as_ref()
+            .and_then(|annotations| annotations.get(id))
+            .cloned()
+    };
+    let text_document_edit = |document_edit: &TextDocumentEdit| {
+        document_edit
+            .edits
+            .iter()
+            .map(|edit| {
+                let (text_edit, annotation) = match edit {
+                    OneOf::Left(text_edit) => (text_edit.clone(), None),
+                    OneOf::Right(annotated) => (
+                        annotated.text_edit.clone(),
+                        annotation(&annotated.annotation_id),
+                    ),
+                };
+                (
+                    document_edit.text_document.uri.clone(),
+                    text_edit,
+                    annotation,
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    Some(match &edit.document_changes {
+        Some(DocumentChanges::Edits(edits)) => edits.iter().flat_map(text_document_edit).collect(),
+        Some(DocumentChanges::Operations(ops)) => {
+            let mut text_edits = vec![];
+            for op in ops {
+                match op {
+                    DocumentChangeOperation::Edit(edit) => {
+                        text_edits.extend(text_document_edit(edit))
+                    }
+                    DocumentChangeOperation::Op(_) => return None,
+                }
+            }
+            text_edits
+        }
+        None => edit
+            .changes
+            .iter()
+            .flatten()
+            .flat_map(|(uri, edits)| {
+                edits
+                    .iter()
+                    .map(move |edit| (uri.clone(), edit.clone(), None))
+            })
+            .collect(),
+    })
+}
+
+/// Whether two edits, given as start and end positions, touch the same text. Insertions at the
+/// same position conflict because their order would be arbitrary.
+fn edits_overlap(
+    (start, end): (KakounePosition, KakounePosition),
+    (other_start, other_end): (KakounePosition, KakounePosition),
+) -> bool {
+    start == other_start || (start < other_end && other_start < end)
+}
+
+/// A text edit of a quick fix. Edits of different servers may use different offset encodings,
+/// so they are kept as Kakoune positions, which also makes it easy to detect overlaps.
+struct FixEdit {
+    path: String,
+    start: KakounePosition,
+    end: KakounePosition,
+    new_text: String,
+    annotation: Option<ChangeAnnotation>,
+}
+
+fn fix_key(fix: &[FixEdit]) -> Vec<(&str, KakounePosition, KakounePosition, &str)> {
+    fix.iter()
+        .map(|edit| {
+            (
+                edit.path.as_str(),
+                edit.start,
+                edit.end,
+                edit.new_text.as_str(),
+            )
+        })
+        .collect()
+}
+
+/// Keeps the fixes that don't overlap with an earlier one, and returns how many were skipped.
+/// Responses arrive in no particular order, so the fixes are sorted by position first, to keep
+/// the same one of two overlapping fixes every time.
+fn pick_fixes(mut fixes: Vec<(ServerId, Vec<FixEdit>)>) -> (Vec<(ServerId, Vec<FixEdit>)>, usize) {
+    fixes.sort_by(|(server_id, fix), (other_server_id, other_fix)| {
+        fix_key(fix)
+            .cmp(&fix_key(other_fix))
+            .then(server_id.cmp(other_server_id))
+    });
+    // Several diagnostics often share one fix.
+    fixes.dedup_by(|(_, fix), (_, other_fix)| fix_key(fix) == fix_key(other_fix));
+
+    let mut picked: Vec<(ServerId, Vec<FixEdit>)> = vec![];
+    let mut conflicting = 0;
+    for (server_id, fix) in fixes {
+        let overlaps = fix.iter().any(|edit| {
+            picked.iter().flat_map(|(_, fix)| fix).any(|other| {
+                edit.path == other.path
+                    && edits_overlap((edit.start, edit.end), (other.start, other.end))
+            })
+        });
+        if overlaps {
+            conflicting += 1;
+            continue;
+        }
+        picked.push((server_id, fix));
+    }
+    (picked, conflicting)
+}
+
+fn editor_code_actions_fix_all(
+    meta: EditorMeta,
+    version: i32,
+    results: Vec<(ServerId, Option<Option<CodeActionResponse>>)>,
+    ctx: &mut Context,
+) {
+    let mut seen = HashSet::new();
+    let mut resolved = vec![];
+    let mut unresolved: HashMap<ServerId, Vec<CodeAction>> = HashMap::new();
+    for (server_id, actions) in results {
+        let Some(action) = actions
+            .flatten()
+            .unwrap_or_default()
+            .into_iter()
+            .find_map(|action| match action {
+                // Commands may have effects we can't check for conflicts.
+                CodeActionOrCommand::CodeAction(
+                    action @ CodeAction {
+                        is_preferred: Some(true),
+                        command: None,
+                        ..
+                    },
+                ) => Some(action),
+                _ => None,
+            })
+        else {
+            continue;
+        };
+        // Identical actions only need to be resolved once.
+        if !seen.insert((server_id, serde_json::to_string(&action).unwrap())) {
+            continue;
+        }
+        if action.edit.is_some() {
+            resolved.push((server_id, action));
+            continue;
+        }
+        let server = ctx.server(server_id);
+        if attempt_server_capability(
+            ctx,
+            (server_id, server),
+            &meta,
+            CAPABILITY_CODE_ACTIONS_RESOLVE,
+        ) {
+            unresolved.entry(server_id).or_default().push(action);
+        }
+    }
+    if unresolved.is_empty() {
+        apply_code_actions_fix_all(meta, version, resolved, ctx);
+        return;
+    }
+    ctx.call_to_completion::<CodeActionResolveRequest, _>(
+        meta,
+        RequestParams::Each(unresolved),
+        move |ctx, meta, results| {
+            resolved.extend(
+                results
+                    .into_iter()
+                    .filter_map(|(server_id, action)| Some((server_id, action?))),
+            );
+            apply_code_actions_fix_all(meta, version, resolved, ctx);
+        },
+    );
+}
+
+fn apply_code_actions_fix_all(
+    meta: EditorMeta,
+    version: i32,
+    actions: Vec<(ServerId, CodeAction)>,
+    ctx: &mut Context,
+) {
+    if ctx
+        .documents
+        .get(&meta.buffile)
+        .map_or(true, |document| document.version != version)
+    {
+        ctx.show_error(
+            meta,
+            "lsp-code-actions-fix-all: buffer was modified while computing fixes",
+        );
+        return;
+    }
+
+    let mut texts: HashMap<String, Rope> = HashMap::new();
+    let mut fixes: Vec<(ServerId, Vec<FixEdit>)> = vec![];
+    for (server_id, action) in actions {
+        // Resolving may have added a command.
+        let (Some(edit), None) = (&action.edit, &action.command) else {
+            continue;
+        };
+        let Some(edits) = workspace_edit_text_edits(edit) else {
+            continue;
+        };
+        let offset_encoding = ctx.server(server_id).offset_encoding;
+        let edit_count = edits.len();
+        let mut fix = vec![];
+        for (uri, edit, annotation) in edits {
+            let path = uri_to_file_path(&uri).to_str().unwrap().to_string();
+            if !texts.contains_key(&path) {
+                if let Some(text) = get_file_contents(&path, ctx) {
+                    texts.insert(path.clone(), text);
+                }
+            }
+            let Some(text) = texts.get(&path) else {
+                break;
+            };
+            fix.push(FixEdit {
+                start: lsp_position_to_kakoune(&edit.range.start, text, offset_encoding),
+                end: lsp_position_to_kakoune(&edit.range.end, text, offset_encoding),
+                path,
+                new_text: edit.new_text,
+                annotation,
+            });
+        }
+        // Some file could not be read.
+        if fix.len() != edit_count {
+            continue;
+        }
+        fixes.push((server_id, fix));
+    }
+    let (picked, conflicting) = pick_fixes(fixes);
+    let Some(&(target_server, _)) = picked.first() else {
+        ctx.show_error(
+            meta,
+            "lsp-code-actions-fix-all: no preferred quick fixes available",
+        );
+        return;
+    };
+    let fixed = picked.len();
+
+    let offset_encoding = ctx.server(target_server).offset_encoding;
+    let mut changes: HashMap<String, Vec<OneOf<TextEdit, AnnotatedTextEdit>>> = HashMap::new();
+    let mut change_annotations = HashMap::new();
+    for edit in picked.into_iter().flat_map(|(_, fix)| fix) {
+        let text = &texts[&edit.path];
+        let text_edit = TextEdit {
+            range: Range {
+                start: kakoune_position_to_lsp(&edit.start, text, offset_encoding),
+                end: kakoune_position_to_lsp(&edit.end, text, offset_encoding),
+            },
+            new_text: edit.new_text,
+        };
+        let text_edit = match edit.annotation {
+            // Annotation identifiers of different fixes may collide, so give each edit its own.
+            Some(annotation) => {
+                let annotation_id = change_annotations.len().to_string();
+                change_annotations.insert(annotation_id.clone(), annotation);
+                OneOf::Right(AnnotatedTextEdit {
+                    text_edit,
+                    annotation_id,
+                })
+            }
+            None => OneOf::Left(text_edit),
+        };
+        changes.entry(edit.path).or_default().push(text_edit);
+    }
+    let edit = WorkspaceEdit {
+        document_changes: Some(DocumentChanges::Edits(
+            changes
+                .into_iter()
+                .map(|(path, edits)| TextDocumentEdit {
+                    text_document: OptionalVersionedTextDocumentIdentifier {
+                        uri: file_path_to_uri(&path),
+                        version: None,
+                    },
+                    edits,
+                })
+                .collect(),
+        )),
+        change_annotations: (!change_annotations.is_empty()).then_some(change_annotations),
+        ..Default::default()
+    };
+    let mut message = format!("lsp-code-actions-fix-all: applied {} fixes", fixed);
+    if conflicting != 0 {
+        let _ = write!(message, ", skipped {} overlapping fixes", conflicting);
+    }
+    // Some fixes may need confirmation, so report only once the edit has been applied.
+    apply_edit_then(
+        target_server,
+        meta,
+        None,
+        edit,
+        ctx,
+        move |ctx, meta, response| {
+            if response.applied {
+                ctx.exec(meta, format!("echo -- {}", editor_quote(&message)))
+            }
+        },
+    );
+}
+
 fn code_action_or_command_to_editor_command(
     server_name: &ServerName,
     action: &CodeActionOrCommand,
@@ -438,3 +808,147 @@ pub fn text_document_code_action_resolve(
         },
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edits_overlap_at_shared_text_or_insertion_point() {
+        let position = |column| KakounePosition { line: 1, column };
+        let edit = |start, end| (position(start), position(end));
+        assert!(edits_overlap(edit(1, 5), edit(3, 8)));
+        assert!(edits_overlap(edit(3, 3), edit(1, 5)));
+        assert!(edits_overlap(edit(3, 3), edit(3, 3)));
+        assert!(!edits_overlap(edit(1, 3), edit(3, 5)));
+        assert!(!edits_overlap(edit(5, 5), edit(1, 5)));
+    }
+
+    #[test]
+    fn pick_fixes_by_position() {
+        let fix = |server_id, start, end, new_text: &str| {
+            let position = |column| KakounePosition { line: 1, column };
+            (
+                server_id,
+                vec![FixEdit {
+                    path: "/src/main.rs".to_string(),
+                    start: position(start),
+                    end: position(end),
+                    new_text: new_text.to_string(),
+                    annotation: None,
+                }],
+            )
+        };
+        let picked_keys = |fixes| {
+            let (picked, conflicting) = pick_fixes(fixes);
+            let keys: Vec<_> = picked
+                .iter()
+                .map(|(server_id, fix)| (*server_id, fix[0].start.column, fix[0].new_text.clone()))
+                .collect();
+            (keys, conflicting)
+        };
+        let expected = (vec![(0, 3, "a".to_string()), (1, 9, "c".to_string())], 1);
+        assert_eq!(
+            picked_keys(vec![
+                fix(1, 5, 8, "b"),
+                fix(0, 3, 6, "a"),
+                fix(1, 9, 9, "c")
+            ]),
+            expected
+        );
+        assert_eq!(
+            picked_keys(vec![
+                fix(1, 9, 9, "c"),
+                fix(0, 3, 6, "a"),
+                fix(1, 5, 8, "b")
+            ]),
+            expected
+        );
+        // Several diagnostics sharing one fix are not counted as overlapping.
+        assert_eq!(
+            picked_keys(vec![fix(0, 3, 6, "a"), fix(0, 3, 6, "a")]),
+            (vec![(0, 3, "a".to_string())], 0)
+        );
+    }
+
+    #[test]
+    fn fix_all_waits_for_every_diagnostic() {
+        let (to_editor, from_ctx) = crossbeam_channel::unbounded::<EditorResponse>();
+        let mut ctx = Context::new(
+            SessionId("session".to_string()),
+            to_editor,
+            Config::default(),
+        );
+        let buffile = "/project/main.rs".to_string();
+        ctx.documents.insert(
+            buffile.clone(),
+            Document {
+                version: 1,
+                text: Rope::from_str("a b c d\n"),
+            },
+        );
+        let capabilities = ServerCapabilities {
+            code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+            ..Default::default()
+        };
+        let (server_id, messages) = ctx.add_mock_server("/project", capabilities);
+        let range = |column| Range::new(Position::new(0, column), Position::new(0, column + 1));
+        let diagnostics = [0, 2, 4, 6]
+            .iter()
+            .map(|&column| {
+                (
+                    server_id,
+                    Diagnostic::new_simple(range(column), "error".into()),
+                )
+            })
+            .collect();
+        ctx.diagnostics.insert(buffile.clone(), diagnostics);
+
+        let meta = EditorMeta {
+            buffile,
+            servers: vec![server_id],
+            ..Default::default()
+        };
+        let params = CodeActionsFixAllParams { filters: vec![] };
+        code_actions_fix_all(meta, params, &mut ctx);
+
+        let timeout = std::time::Duration::from_secs(1);
+        for _ in 0..4 {
+            let Ok(ServerMessage::Request(jsonrpc_core::Call::MethodCall(call))) =
+                messages.recv_timeout(timeout)
+            else {
+                panic!("expected a code action request");
+            };
+            let params: CodeActionParams = call.params.parse().unwrap();
+            let fix = CodeAction {
+                title: "fix".to_string(),
+                kind: Some(CodeActionKind::QUICKFIX),
+                edit: Some(WorkspaceEdit {
+                    changes: Some(HashMap::from([(
+                        params.text_document.uri,
+                        vec![TextEdit::new(params.range, "x".to_string())],
+                    )])),
+                    ..Default::default()
+                }),
+                is_preferred: Some(true),
+                ..Default::default()
+            };
+            let result = vec![CodeActionOrCommand::CodeAction(fix)];
+            let success = jsonrpc_core::Success {
+                jsonrpc: Some(jsonrpc_core::Version::V2),
+                result: serde_json::to_value(result).unwrap(),
+                id: call.id,
+            };
+            let _ = crate::controller::process_server_response(
+                &mut ctx,
+                server_id,
+                jsonrpc_core::Output::Success(success),
+            );
+        }
+
+        assert!(ctx.batches.is_empty());
+        assert!(from_ctx
+            .try_iter()
+            .any(|response| response.command.contains("applied 4 fixes")));
+    }
+}
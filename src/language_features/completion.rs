@@ -13,10 +13,143 @@ use lazy_static::lazy_static;
 use lsp_types::request::*;
 use lsp_types::*;
 use regex::Regex;
-use std::collections::HashMap;
+use ropey::Rope;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::cmp::min;
+use std::collections::{HashMap, HashSet};
 use std::convert::TryFrom;
 use unicode_width::UnicodeWidthStr;
 
+/// textDocument/completion, but with support for the item defaults of completion lists,
+/// which lsp-types doesn't know about.
+enum CompletionRequest {}
+
+impl Request for CompletionRequest {
+    type Params = CompletionParams;
+    type Result = Option<CompletionResult>;
+    const METHOD: &'static str = Completion::METHOD;
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+enum CompletionResult {
+    Array(Vec<CompletionItem>),
+    List(CompletionListWithDefaults),
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CompletionListWithDefaults {
+    is_incomplete: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    item_defaults: Option<CompletionItemDefaults>,
+    items: Vec<CompletionItem>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CompletionItemDefaults {
+    commit_characters: Option<Vec<String>>,
+    edit_range: Option<CompletionEditRange>,
+    insert_text_format: Option<InsertTextFormat>,
+    insert_text_mode: Option<InsertTextMode>,
+    data: Option<Value>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+enum CompletionEditRange {
+    Range(Range),
+    InsertAndReplace { insert: Range, replace: Range },
+}
+
+/// Fills in the properties that the item leaves to the defaults of its completion list.
+fn apply_item_defaults(item: &mut CompletionItem, defaults: &CompletionItemDefaults) {
+    if item.commit_characters.is_none() {
+        item.commit_characters
+            .clone_from(&defaults.commit_characters);
+    }
+    if item.text_edit.is_none() {
+        // lsp-types drops textEditText, so fall back to the insert text like other clients.
+        let new_text = item.insert_text.as_ref().unwrap_or(&item.label).clone();
+        item.text_edit = match defaults.edit_range {
+            Some(CompletionEditRange::Range(range)) => {
+                Some(CompletionTextEdit::Edit(TextEdit { range, new_text }))
+            }
+            Some(CompletionEditRange::InsertAndReplace { insert, replace }) => {
+                Some(CompletionTextEdit::InsertAndReplace(InsertReplaceEdit {
+                    new_text,
+                    insert,
+                    replace,
+                }))
+            }
+            None => None,
+        };
+    }
+    if item.insert_text_format.is_none() {
+        item.insert_text_format = defaults.insert_text_format;
+    }
+    if item.insert_text_mode.is_none() {
+        item.insert_text_mode = defaults.insert_text_mode;
+    }
+    if item.data.is_none() {
+        item.data.clone_from(&defaults.data);
+    }
+}
+
+/// Tells the server whether we are completing the same word again after it returned an
+/// incomplete list, or whether the user just typed one of its trigger characters.
+fn completion_context(
+    meta: &EditorMeta,
+    params: &TextDocumentCompletionParams,
+    server_id: ServerId,
+    server: &ServerSettings,
+    ctx: &Context,
+) -> CompletionContext {
+    let offset_position = KakounePosition {
+        line: params.position.line,
+        column: params.completion.offset,
+    };
+    let is_incomplete = ctx.completion_last_client == meta.client
+        && ctx
+            .completion_incomplete
+            .as_ref()
+            .is_some_and(|(buffile, position, servers)| {
+                *buffile == meta.buffile
+                    && *position == offset_position
+                    && servers.contains(&server_id)
+            });
+    if is_incomplete {
+        return CompletionContext {
+            trigger_kind: CompletionTriggerKind::TRIGGER_FOR_INCOMPLETE_COMPLETIONS,
+            trigger_character: None,
+        };
+    }
+    let previous_character = ctx.documents.get(&meta.buffile).and_then(|document| {
+        let line = document.text.get_line(params.position.line as usize - 1)?;
+        let byte = min(params.position.column as usize - 1, line.len_bytes());
+        let char_idx = line.try_byte_to_char(byte).ok()?.checked_sub(1)?;
+        Some(line.char(char_idx).to_string())
+    });
+    let trigger_character = previous_character.filter(|ch| {
+        server
+            .capabilities
+            .as_ref()
+            .and_then(|caps| caps.completion_provider.as_ref())
+            .and_then(|compl| compl.trigger_characters.as_ref())
+            .is_some_and(|trigger_characters| trigger_characters.contains(ch))
+    });
+    CompletionContext {
+        trigger_kind: if trigger_character.is_some() {
+            CompletionTriggerKind::TRIGGER_CHARACTER
+        } else {
+            CompletionTriggerKind::INVOKED
+        },
+        trigger_character,
+    }
+}
+
 pub fn text_document_completion(
     meta: EditorMeta,
     params: TextDocumentCompletionParams,
@@ -45,14 +178,20 @@ pub fn text_document_completion(
                         )
                         .unwrap(),
                     },
-                    context: None,
+                    context: Some(completion_context(
+                        &meta,
+                        &params,
+                        server_id,
+                        server_settings,
+                        ctx,
+                    )),
                     work_done_progress_params: Default::default(),
                     partial_result_params: Default::default(),
                 }],
             )
         })
         .collect();
-    ctx.call::<Completion, _>(
+    ctx.call::<CompletionRequest, _>(
         meta,
         RequestParams::Each(req_params),
         |ctx: &mut Context, meta, results| editor_completion(meta, params, results, ctx),
@@ -63,18 +202,103 @@ fn sort_text(item: &CompletionItem) -> &str {
     item.sort_text.as_ref().unwrap_or(&item.label)
 }
 
+/// Returns the edit that accepting the item should make, if the server specified one.
+fn completion_text_edit(item: &CompletionItem, prefer_replace: bool) -> Option<TextEdit> {
+    match item.text_edit.as_ref()? {
+        CompletionTextEdit::Edit(text_edit) => Some(text_edit.clone()),
+        CompletionTextEdit::InsertAndReplace(edit) => Some(TextEdit {
+            range: if prefer_replace {
+                edit.replace
+            } else {
+                edit.insert
+            },
+            new_text: edit.new_text.clone(),
+        }),
+    }
+}
+
+/// Escapes text so it's inserted literally by lsp-snippets-insert.
+fn escape_snippet(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('$', "\\$")
+        .replace('}', "\\}")
+}
+
+/// Kakoune replaces the text between the completion offset and the cursor with the inserted
+/// completion, which is deleted again before applying a text edit. Returns the start and the
+/// inclusive end (None if empty) of the edit's range in the buffer at that point.
+fn text_edit_range_after_completion(
+    range: &Range,
+    text: &Rope,
+    offset_encoding: OffsetEncoding,
+    offset: KakounePosition,
+    cursor: KakounePosition,
+) -> (KakounePosition, Option<KakounePosition>) {
+    let kakoune_byte = |position: KakounePosition| {
+        let line = min(position.line as usize - 1, text.len_lines() - 1);
+        min(
+            text.line_to_byte(line) + position.column as usize - 1,
+            text.len_bytes(),
+        )
+    };
+    let lsp_byte = |position: &Position| match text.get_line(position.line as usize) {
+        Some(line) => {
+            text.line_to_byte(position.line as usize)
+                + lsp_character_to_byte_offset(line, position.character as usize, offset_encoding)
+                    .unwrap_or(line.len_bytes())
+        }
+        None => text.len_bytes(),
+    };
+    let (offset, cursor) = (kakoune_byte(offset), kakoune_byte(cursor));
+    let cursor = cursor.max(offset);
+    let mut rest = text.clone();
+    rest.remove(rest.byte_to_char(offset)..rest.byte_to_char(cursor));
+
+    let adjust = |byte: usize| {
+        if byte < offset {
+            byte
+        } else if byte <= cursor {
+            offset
+        } else {
+            byte - (cursor - offset)
+        }
+    };
+    let position = |byte: usize| {
+        let line = rest.byte_to_line(byte);
+        KakounePosition {
+            line: line as u32 + 1,
+            column: (byte - rest.line_to_byte(line)) as u32 + 1,
+        }
+    };
+    let start = adjust(lsp_byte(&range.start));
+    let end = adjust(lsp_byte(&range.end));
+    let last = (end > start).then(|| rest.char_to_byte(rest.byte_to_char(end) - 1));
+    (position(start), last.map(position))
+}
+
 fn editor_completion(
     meta: EditorMeta,
     params: TextDocumentCompletionParams,
-    results: Vec<(ServerId, Option<CompletionResponse>)>,
+    results: Vec<(ServerId, Option<CompletionResult>)>,
     ctx: &mut Context,
 ) {
+    let mut incomplete_servers = HashSet::new();
     let mut items: Vec<(ServerId, CompletionItem)> = results
         .into_iter()
         .flat_map(|(server_id, items)| {
             let items = match items {
-                Some(CompletionResponse::Array(items)) => items,
-                Some(CompletionResponse::List(list)) => list.items,
+                Some(CompletionResult::Array(items)) => items,
+                Some(CompletionResult::List(mut list)) => {
+                    if list.is_incomplete {
+                        incomplete_servers.insert(server_id);
+                    }
+                    if let Some(defaults) = &list.item_defaults {
+                        for item in &mut list.items {
+                            apply_item_defaults(item, defaults);
+                        }
+                    }
+                    list.items
+                }
                 None => vec![],
             };
 
@@ -83,8 +307,12 @@ fn editor_completion(
         .collect();
 
     // TODO Group by server?
+    // Preselected items come first, so they are the first to be selected in the menu.
     items.sort_by(|(_left_server, left), (_right_server, right)| {
-        sort_text(left).cmp(sort_text(right))
+        let is_preselected = |item: &CompletionItem| item.preselect == Some(true);
+        is_preselected(right)
+            .cmp(&is_preselected(left))
+            .then_with(|| sort_text(left).cmp(sort_text(right)))
     });
 
     let version = meta.version;
@@ -94,25 +322,85 @@ fn editor_completion(
     if ctx.completion_last_client != meta.client {
         ctx.completion_last_client.clone_from(&meta.client);
     }
+    // Kakoune requests completions again as the user keeps typing, which lets these servers
+    // extend their lists.
+    ctx.completion_incomplete = (!incomplete_servers.is_empty()).then(|| {
+        (
+            meta.buffile.clone(),
+            KakounePosition {
+                line: params.position.line,
+                column: params.completion.offset,
+            },
+            incomplete_servers,
+        )
+    });
 
     if items.is_empty() {
         return;
     }
+    let Some(document) = ctx.documents.get(&meta.buffile) else {
+        warn!(
+            ctx.to_editor(),
+            "No document in context for file: {}", &meta.buffile
+        );
+        return;
+    };
 
-    // Maximum display width of any completion label.
-    let maxwidth = items
+    // Maximum display widths of the columns of the menu.
+    let label_width = items
         .iter()
-        .map(|(_, x)| UnicodeWidthStr::width(x.label.as_str()))
+        .map(|(_, x)| UnicodeWidthStr::width(x.label.as_str()) + label_detail(x).width())
+        .max()
+        .unwrap_or(0);
+    let description_width = items
+        .iter()
+        .map(|(_, x)| label_description(x).width())
         .max()
         .unwrap_or(0);
 
-    let mut inferred_offset: Option<u32> = None;
-    let mut can_infer_offset = true;
+    let text_edits: Vec<_> = items
+        .iter()
+        .map(|(server_id, x)| {
+            let server = ctx.server(*server_id);
+            completion_text_edit(x, params.completion.prefer_replace).map(|text_edit| {
+                let range =
+                    lsp_range_to_kakoune(&text_edit.range, &document.text, server.offset_encoding);
+                let cursor = get_lsp_position(server, &meta.buffile, &params.position, ctx);
+                (text_edit, range, cursor)
+            })
+        })
+        .collect();
+
+    // If all edits replace the text from the same column up to the cursor, Kakoune will make
+    // these edits if we simply pass the replacement strings as completions.
+    let is_simple_text_edit = |(text_edit, range, cursor): &(TextEdit, KakouneRange, _)| {
+        range.start.line == params.position.line
+            && text_edit.range.start.line == text_edit.range.end.line
+            && Some(text_edit.range.end) == *cursor
+            && !text_edit.new_text.contains('\n')
+    };
+    let inferred_offset = text_edits
+        .iter()
+        .map(|text_edit| {
+            text_edit
+                .as_ref()
+                .filter(|text_edit| is_simple_text_edit(text_edit))
+                .map(|(_, range, _)| range.start.column)
+        })
+        .all_equal_value()
+        .ok()
+        .flatten();
+    let offset = inferred_offset.unwrap_or(params.completion.offset);
+    let offset_position = KakounePosition {
+        line: params.position.line,
+        column: offset,
+    };
 
     let items = items
         .iter()
+        .zip(text_edits)
         .enumerate()
-        .map(|(completion_item_index, (server_id, x))| {
+        .map(|(completion_item_index, ((server_id, x), text_edit))| {
             let server = ctx.server(*server_id);
             let maybe_resolve = if server
                 .capabilities
@@ -131,74 +419,72 @@ fn editor_completion(
             } else {
                 ""
             };
+            // Typing one of these characters accepts the completion before inserting it.
+            let maybe_commit_characters = x
+                .commit_characters
+                .as_ref()
+                .or_else(|| {
+                    server
+                        .capabilities
+                        .as_ref()
+                        .and_then(|caps| caps.completion_provider.as_ref())
+                        .and_then(|compl| compl.all_commit_characters.as_ref())
+                })
+                .filter(|commit_characters| !commit_characters.is_empty())
+                .map(|commit_characters| {
+                    format!(
+                        "lsp-completion-commit-characters {}\n",
+                        editor_quote(
+                            &commit_characters
+                                .iter()
+                                .map(|ch| escape_kakoune_regex(ch))
+                                .join("|")
+                        )
+                    )
+                })
+                .unwrap_or_default();
             let on_select = formatdoc!(
                 "lsp-completion-item-selected {completion_item_index}
-                 {maybe_resolve}info -markup -style menu -- %§{}§",
+                 {maybe_commit_characters}{maybe_resolve}info -markup -style menu -- %§{}§",
                 completion_menu_text(ctx.to_editor(), x).replace('§', "§§")
             );
 
-            let entry = match x.kind {
-                Some(k) => format!(
-                    "{}{} {{MenuInfo}}{:?}",
-                    escape_kakoune_markup(&x.label),
-                    " ".repeat(maxwidth - UnicodeWidthStr::width(x.label.as_str())),
-                    k
-                ),
-                None => escape_kakoune_markup(&x.label),
-            };
-
-            let is_simple_text_edit = x.text_edit.as_ref().is_some_and(|cte| {
-                let document = match ctx.documents.get(&meta.buffile) {
-                    Some(doc) => doc,
-                    None => {
-                        warn!(
-                            ctx.to_editor(),
-                            "No document in context for file: {}", &meta.buffile
-                        );
-                        return false;
-                    }
-                };
+            let entry = completion_menu_entry(
+                x,
+                &params.completion.kind_icons,
+                label_width,
+                description_width,
+            );
 
-                match cte {
-                    CompletionTextEdit::Edit(text_edit) => {
-                        // The generic textEdit property is not supported yet (#40).  However,
-                        // we can support simple text edits that only replace the token left
-                        // of the cursor. Kakoune will do this very edit if we simply pass it
-                        // the replacement string as completion.
-                        let range = lsp_range_to_kakoune(
-                            &text_edit.range,
-                            &document.text,
-                            server.offset_encoding,
-                        );
-
-                        if can_infer_offset {
-                            match inferred_offset {
-                                None => inferred_offset = Some(range.start.column),
-                                Some(offset) if offset != range.start.column => {
-                                    can_infer_offset = false;
-                                    inferred_offset = None
-                                }
-                                _ => (),
-                            }
-                        };
-                        range.start.line == params.position.line
-                            && range.end.line == params.position.line
-                    }
-                    CompletionTextEdit::InsertAndReplace(_) => false,
+            // Text edits that Kakoune can't make itself replace the inserted completion
+            // once it's accepted.
+            let select_text_edit_range = text_edit.as_ref().and_then(|text_edit| {
+                let (_, range, _) = text_edit;
+                if is_simple_text_edit(text_edit) && range.start.column == offset {
+                    return None;
                 }
+                let (start, end) = text_edit_range_after_completion(
+                    &text_edit.0.range,
+                    &document.text,
+                    server.offset_encoding,
+                    offset_position,
+                    params.position,
+                );
+                Some(match end {
+                    Some(end) => format!(
+                        "select {}\nexecute-keys <a-;>d",
+                        ForwardKakouneRange(KakouneRange { start, end })
+                    ),
+                    None => format!(
+                        "select {}",
+                        ForwardKakouneRange(KakouneRange { start, end: start })
+                    ),
+                })
             });
-            if !is_simple_text_edit {
-                can_infer_offset = false;
-                inferred_offset = None;
-            }
             let specified_insert_text = x.insert_text.as_ref().unwrap_or(&x.label);
-            let eventual_insert_text = x
-                .text_edit
+            let eventual_insert_text = text_edit
                 .as_ref()
-                .map(|cte| match cte {
-                    CompletionTextEdit::Edit(text_edit) => &text_edit.new_text,
-                    CompletionTextEdit::InsertAndReplace(text_edit) => &text_edit.new_text,
-                })
+                .map(|(text_edit, _, _)| &text_edit.new_text)
                 .unwrap_or(specified_insert_text);
 
             fn completion_entry(insert_text: &str, on_select: &str, menu: &str) -> String {
@@ -209,6 +495,15 @@ fn editor_completion(
                     escape_tuple_element(menu),
                 ))
             }
+            let insert_completion = |snippet: &str| {
+                let mut command =
+                    format!("lsp-snippets-insert-completion {}", editor_quote(snippet));
+                if let Some(select_text_edit_range) = &select_text_edit_range {
+                    command.push(' ');
+                    command.push_str(&editor_quote(select_text_edit_range));
+                }
+                command
+            };
 
             // If snippet support is both enabled and provided by the server,
             // we'll need to perform some transformations on the completion commands.
@@ -237,8 +532,8 @@ fn editor_completion(
 
                 let on_select = formatdoc!(
                     "{on_select}
-                     lsp-snippets-insert-completion {}",
-                    editor_quote(&snippet)
+                     {}",
+                    insert_completion(&snippet)
                 );
 
                 completion_entry(&insert_text, &on_select, &entry)
@@ -246,13 +541,15 @@ fn editor_completion(
                 // Due to implementation reasons, we currently do not support filter text
                 // with snippets.
                 let specified_filter_text = x.filter_text.as_ref().unwrap_or(&x.label);
-                let (insert_text, on_select) = if specified_filter_text != eventual_insert_text {
+                let (insert_text, on_select) = if specified_filter_text != eventual_insert_text
+                    || select_text_edit_range.is_some()
+                {
                     // Simulate filter-text support by giving the filter-text to Kakoune
                     // but expand to the insert-text when the completion is accepted.
                     let on_select = formatdoc!(
                         "{on_select}
-                         lsp-snippets-insert-completion {}",
-                        editor_quote(&(eventual_insert_text.to_string() + "$0"))
+                         {}",
+                        insert_completion(&(escape_snippet(eventual_insert_text) + "$0"))
                     );
                     (specified_filter_text, on_select)
                 } else {
@@ -264,7 +561,6 @@ fn editor_completion(
         .join(" ");
 
     let line = params.position.line;
-    let offset = inferred_offset.unwrap_or(params.completion.offset);
     let command = formatdoc!(
         "set-option window lsp_completions {line}.{offset}@{version} {items}
          set-option window lsp_completions_timestamp {version}"
@@ -272,6 +568,72 @@ fn editor_completion(
     ctx.exec(meta, command);
 }
 
+fn label_detail(item: &CompletionItem) -> &str {
+    item.label_details
+        .as_ref()
+        .and_then(|details| details.detail.as_deref())
+        .unwrap_or_default()
+}
+
+fn label_description(item: &CompletionItem) -> &str {
+    item.label_details
+        .as_ref()
+        .and_then(|details| details.description.as_deref())
+        .unwrap_or_default()
+}
+
+fn is_deprecated(item: &CompletionItem) -> bool {
+    #[allow(deprecated)]
+    let deprecated = item.deprecated == Some(true);
+    deprecated
+        || item
+            .tags
+            .as_ref()
+            .is_some_and(|tags| tags.contains(&CompletionItemTag::DEPRECATED))
+}
+
+/// Formats the menu line of a completion: the label followed by its detail (e.g. the
+/// signature), then the description (e.g. the module path) right-aligned and the kind.
+fn completion_menu_entry(
+    item: &CompletionItem,
+    kind_icons: &HashMap<String, String>,
+    label_width: usize,
+    description_width: usize,
+) -> String {
+    let mut entry = escape_kakoune_markup(&item.label);
+    if is_deprecated(item) {
+        entry = format!("{{CompletionItemDeprecated}}{}{{MenuInfo}}", entry);
+    }
+    let detail = label_detail(item);
+    let description = label_description(item);
+    let kind = item.kind.map(|kind| {
+        let name = format!("{:?}", kind);
+        kind_icons.get(&name).cloned().unwrap_or(name)
+    });
+    if detail.is_empty() && description_width == 0 && kind.is_none() {
+        return entry;
+    }
+    if !is_deprecated(item) {
+        entry.push_str("{MenuInfo}");
+    }
+    entry.push_str(&escape_kakoune_markup(detail));
+    if description_width == 0 && kind.is_none() {
+        return entry;
+    }
+    let padding = label_width - UnicodeWidthStr::width(item.label.as_str()) - detail.width()
+        + description_width
+        - description.width();
+    entry.push_str(&" ".repeat(padding + 1));
+    entry.push_str(&escape_kakoune_markup(description));
+    if let Some(kind) = kind {
+        if description_width != 0 {
+            entry.push(' ');
+        }
+        entry.push_str(&escape_kakoune_markup(&kind));
+    }
+    entry
+}
+
 fn completion_menu_text(to_editor: &ToEditorSender, x: &CompletionItem) -> String {
     // Combine the 'detail' line and the full-text documentation into
     // a single string. If both exist, separate them with a horizontal rule.
@@ -407,3 +769,121 @@ fn editor_completion_item_resolve(
         apply_text_edits(server_id, meta, uri, resolved_edits.clone(), ctx)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_edit_range_after_completion_replace_and_insert() {
+        let text = Rope::from_str("foo.bar_baz qux\n");
+        let offset = KakounePosition { line: 1, column: 5 };
+        let cursor = KakounePosition { line: 1, column: 8 };
+        let range = |start, end| Range::new(Position::new(0, start), Position::new(0, end));
+
+        let replace = text_edit_range_after_completion(
+            &range(4, 11),
+            &text,
+            OffsetEncoding::Utf8,
+            offset,
+            cursor,
+        );
+        assert_eq!(
+            replace,
+            (
+                KakounePosition { line: 1, column: 5 },
+                Some(KakounePosition { line: 1, column: 8 })
+            )
+        );
+
+        let insert = text_edit_range_after_completion(
+            &range(4, 7),
+            &text,
+            OffsetEncoding::Utf8,
+            offset,
+            cursor,
+        );
+        assert_eq!(insert, (KakounePosition { line: 1, column: 5 }, None));
+
+        let before_offset = text_edit_range_after_completion(
+            &range(0, 7),
+            &text,
+            OffsetEncoding::Utf8,
+            offset,
+            cursor,
+        );
+        assert_eq!(
+            before_offset,
+            (
+                KakounePosition { line: 1, column: 1 },
+                Some(KakounePosition { line: 1, column: 4 })
+            )
+        );
+    }
+
+    #[test]
+    fn item_defaults_fill_missing_properties() {
+        let result: CompletionResult = serde_json::from_value(serde_json::json!({
+            "isIncomplete": true,
+            "itemDefaults": {
+                "editRange": {
+                    "insert": {"start": {"line": 0, "character": 4}, "end": {"line": 0, "character": 6}},
+                    "replace": {"start": {"line": 0, "character": 4}, "end": {"line": 0, "character": 8}}
+                },
+                "insertTextFormat": 2,
+                "data": 42
+            },
+            "items": [
+                {"label": "foo", "insertText": "foo($0)"},
+                {"label": "bar", "data": 1}
+            ]
+        }))
+        .unwrap();
+        let CompletionResult::List(mut list) = result else {
+            panic!("expected a completion list");
+        };
+        assert!(list.is_incomplete);
+        let defaults = list.item_defaults.take().unwrap();
+        for item in &mut list.items {
+            apply_item_defaults(item, &defaults);
+        }
+
+        let foo = &list.items[0];
+        assert_eq!(
+            completion_text_edit(foo, true).map(|edit| (edit.range.end.character, edit.new_text)),
+            Some((8, "foo($0)".to_string()))
+        );
+        assert_eq!(foo.insert_text_format, Some(InsertTextFormat::SNIPPET));
+        assert_eq!(foo.data, Some(Value::from(42)));
+        assert_eq!(list.items[1].data, Some(Value::from(1)));
+    }
+
+    #[test]
+    fn completion_menu_entry_aligns_label_details() {
+        let item = CompletionItem {
+            label: "foo".to_string(),
+            label_details: Some(CompletionItemLabelDetails {
+                detail: Some("(x)".to_string()),
+                description: Some("std".to_string()),
+            }),
+            kind: Some(CompletionItemKind::FUNCTION),
+            tags: Some(vec![CompletionItemTag::DEPRECATED]),
+            ..Default::default()
+        };
+        let kind_icons = HashMap::from([("Function".to_string(), "ƒ".to_string())]);
+        assert_eq!(
+            completion_menu_entry(&item, &kind_icons, 8, 5),
+            "{CompletionItemDeprecated}foo{MenuInfo}(x)     std ƒ"
+        );
+
+        let item = CompletionItem {
+            label: "bar".to_string(),
+            kind: Some(CompletionItemKind::VARIABLE),
+            ..Default::default()
+        };
+        assert_eq!(
+            completion_menu_entry(&item, &HashMap::new(), 5, 0),
+            "bar{MenuInfo}   Variable"
+        );
+    }
+}
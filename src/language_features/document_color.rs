@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+
+use crate::capabilities::{attempt_server_capability, CAPABILITY_DOCUMENT_COLOR};
+use crate::context::*;
+use crate::position::*;
+use crate::text_edit::apply_text_edits_to_buffer;
+use crate::types::*;
+use crate::util::*;
+use itertools::Itertools;
+use lsp_types::request::*;
+use lsp_types::*;
+
+fn request_document_colors(
+    meta: EditorMeta,
+    ctx: &mut Context,
+    callback: impl FnOnce(&mut Context, EditorMeta, Vec<(ServerId, ColorInformation)>) + 'static,
+) {
+    let eligible_servers: Vec<_> = ctx
+        .servers(&meta)
+        .filter(|srv| attempt_server_capability(ctx, *srv, &meta, CAPABILITY_DOCUMENT_COLOR))
+        .collect();
+    if eligible_servers.is_empty() {
+        return;
+    }
+
+    let req_params = eligible_servers
+        .into_iter()
+        .map(|(server_id, _)| {
+            (
+                server_id,
+                vec![DocumentColorParams {
+                    text_document: TextDocumentIdentifier {
+                        uri: file_path_to_uri(&meta.buffile),
+                    },
+                    work_done_progress_params: Default::default(),
+                    partial_result_params: Default::default(),
+                }],
+            )
+        })
+        .collect();
+    ctx.call::<DocumentColor, _>(
+        meta,
+        RequestParams::Each(req_params),
+        move |ctx, meta, results| {
+            let colors = results
+                .into_iter()
+                .flat_map(|(server_id, colors)| {
+                    let colors: Vec<_> =
+                        colors.into_iter().map(|color| (server_id, color)).collect();
+                    colors
+                })
+                .collect();
+            callback(ctx, meta, colors)
+        },
+    );
+}
+
+/// Formats a color as a Kakoune face. Kakoune faces have no alpha channel, so it's ignored.
+fn color_face(color: &Color) -> String {
+    let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    format!(
+        "rgb:{:02x}{:02x}{:02x}",
+        channel(color.red),
+        channel(color.green),
+        channel(color.blue)
+    )
+}
+
+pub fn document_colors(meta: EditorMeta, ctx: &mut Context) {
+    request_document_colors(meta, ctx, |ctx, meta, colors| {
+        let Some(document) = ctx.documents.get(&meta.buffile) else {
+            return;
+        };
+        // Show a swatch in front of each color literal.
+        let ranges = colors
+            .into_iter()
+            .map(|(server_id, info)| {
+                let server = ctx.server(server_id);
+                let range =
+                    lsp_range_to_kakoune(&info.range, &document.text, server.offset_encoding);
+                editor_quote(&format!(
+                    "{}.{}+0|{{{}}}■{{Default}} ",
+                    range.start.line,
+                    range.start.column,
+                    color_face(&info.color)
+                ))
+            })
+            .join(" ");
+        let version = meta.version;
+        let command = format!("set-option buffer lsp_document_colors {version} {ranges}");
+        let command = format!(
+            "evaluate-commands -buffer {} -verbatim -- {}",
+            editor_quote(&meta.buffile),
+            &command
+        );
+        ctx.exec(meta, command)
+    });
+}
+
+/// Replaces the color at the cursor with the server's next presentation of it, for example
+/// hex, rgb() and hsl() notation.
+pub fn color_presentation_next(meta: EditorMeta, params: PositionParams, ctx: &mut Context) {
+    request_document_colors(meta, ctx, move |ctx, meta, colors| {
+        let Some(document) = ctx.documents.get(&meta.buffile) else {
+            return;
+        };
+        let Some((server_id, info)) = colors.into_iter().find(|(server_id, info)| {
+            let server = ctx.server(*server_id);
+            let range = lsp_range_to_kakoune(&info.range, &document.text, server.offset_encoding);
+            range.start <= params.position && params.position <= range.end
+        }) else {
+            ctx.show_error(meta, "lsp-color-presentation-next: no color at cursor");
+            return;
+        };
+
+        let mut req_params = HashMap::new();
+        req_params.insert(
+            server_id,
+            vec![ColorPresentationParams {
+                text_document: TextDocumentIdentifier {
+                    uri: file_path_to_uri(&meta.buffile),
+                },
+                color: info.color,
+                range: info.range,
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+            }],
+        );
+        ctx.call::<ColorPresentationRequest, _>(
+            meta,
+            RequestParams::Each(req_params),
+            move |ctx, meta, mut results| {
+                let presentations = results
+                    .pop()
+                    .map(|(_, presentations)| presentations)
+                    .unwrap_or_default();
+                editor_color_presentation_next(meta, server_id, info.range, presentations, ctx)
+            },
+        );
+    });
+}
+
+fn editor_color_presentation_next(
+    meta: EditorMeta,
+    server_id: ServerId,
+    range: Range,
+    presentations: Vec<ColorPresentation>,
+    ctx: &mut Context,
+) {
+    let Some(document) = ctx.documents.get(&meta.buffile) else {
+        return;
+    };
+    if presentations.is_empty() {
+        ctx.show_error(meta, "lsp-color-presentation-next: no color presentations");
+        return;
+    }
+    let server = ctx.server(server_id);
+    let current = lsp_range_text(&range, &document.text, server.offset_encoding);
+    let presentation_text = |presentation: &ColorPresentation| {
+        presentation
+            .text_edit
+            .as_ref()
+            .map_or(&presentation.label, |edit| &edit.new_text)
+            .clone()
+    };
+    // Pick the presentation after the one currently in the buffer.
+    let next = presentations
+        .iter()
+        .position(|presentation| Some(presentation_text(presentation)) == current)
+        .map_or(0, |i| (i + 1) % presentations.len());
+    let presentation = presentations.into_iter().nth(next).unwrap();
+
+    let mut text_edits = vec![presentation.text_edit.clone().unwrap_or_else(|| TextEdit {
+        range,
+        new_text: presentation.label.clone(),
+    })];
+    text_edits.extend(presentation.additional_text_edits.unwrap_or_default());
+    let Some(cmd) = apply_text_edits_to_buffer(
+        ctx.to_editor(),
+        &meta.client,
+        None,
+        text_edits,
+        &document.text,
+        server.offset_encoding,
+        false,
+    ) else {
+        return;
+    };
+    ctx.exec(meta, cmd);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn color_face_rounds_channels() {
+        let color = Color {
+            red: 1.0,
+            green: 0.5,
+            blue: 0.0,
+            alpha: 1.0,
+        };
+        assert_eq!(color_face(&color), "rgb:ff8000");
+    }
+}
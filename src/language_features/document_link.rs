@@ -0,0 +1,241 @@
+use std::collections::HashMap;
+
+use crate::capabilities::{attempt_server_capability, CAPABILITY_DOCUMENT_LINK};
+use crate::context::*;
+use crate::position::*;
+use crate::show_message::open_document;
+use crate::types::*;
+use crate::util::*;
+use itertools::Itertools;
+use lsp_types::request::*;
+use lsp_types::*;
+
+fn request_document_links(
+    meta: EditorMeta,
+    ctx: &mut Context,
+    callback: impl FnOnce(&mut Context, EditorMeta, Vec<(ServerId, DocumentLink)>) + 'static,
+) {
+    let eligible_servers: Vec<_> = ctx
+        .servers(&meta)
+        .filter(|srv| attempt_server_capability(ctx, *srv, &meta, CAPABILITY_DOCUMENT_LINK))
+        .collect();
+    if eligible_servers.is_empty() {
+        return;
+    }
+
+    let req_params = eligible_servers
+        .into_iter()
+        .map(|(server_id, _)| {
+            (
+                server_id,
+                vec![DocumentLinkParams {
+                    text_document: TextDocumentIdentifier {
+                        uri: file_path_to_uri(&meta.buffile),
+                    },
+                    work_done_progress_params: Default::default(),
+                    partial_result_params: Default::default(),
+                }],
+            )
+        })
+        .collect();
+    ctx.call::<DocumentLinkRequest, _>(
+        meta,
+        RequestParams::Each(req_params),
+        move |ctx, meta, results| {
+            let links = results
+                .into_iter()
+                .flat_map(|(server_id, links)| {
+                    let links: Vec<_> = links
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|link| (server_id, link))
+                        .collect();
+                    links
+                })
+                .collect();
+            callback(ctx, meta, links)
+        },
+    );
+}
+
+fn server_supports_resolve(ctx: &Context, server_id: ServerId) -> bool {
+    ctx.server(server_id)
+        .capabilities
+        .as_ref()
+        .and_then(|caps| caps.document_link_provider.as_ref())
+        .is_some_and(|options| options.resolve_provider == Some(true))
+}
+
+/// Asks servers for the targets of links that don't have one yet, if they support it.
+fn resolve_document_links(
+    meta: EditorMeta,
+    links: Vec<(ServerId, DocumentLink)>,
+    ctx: &mut Context,
+    callback: impl FnOnce(&mut Context, EditorMeta, Vec<(ServerId, DocumentLink)>) + 'static,
+) {
+    let (unresolved, mut resolved): (Vec<_>, Vec<_>) =
+        links.into_iter().partition(|(server_id, link)| {
+            link.target.is_none() && server_supports_resolve(ctx, *server_id)
+        });
+    if unresolved.is_empty() {
+        callback(ctx, meta, resolved);
+        return;
+    }
+    let mut req_params: HashMap<ServerId, Vec<DocumentLink>> = HashMap::new();
+    for (server_id, link) in unresolved {
+        req_params.entry(server_id).or_default().push(link);
+    }
+    ctx.call::<DocumentLinkResolve, _>(
+        meta,
+        RequestParams::Each(req_params),
+        move |ctx, meta, results| {
+            resolved.extend(results);
+            callback(ctx, meta, resolved)
+        },
+    );
+}
+
+/// Splits a "#L<line>,<column>" fragment, as used by some servers, off a link target.
+fn link_target_selection(target: &Uri) -> (Uri, Option<Range>) {
+    let Some(mut url) = url::Url::parse(target.as_str())
+        .ok()
+        .filter(|url| url.scheme() == "file")
+    else {
+        return (target.clone(), None);
+    };
+    let selection = url.fragment().and_then(|fragment| {
+        let (line, column) = match fragment.strip_prefix('L')?.split_once(',') {
+            Some((line, column)) => (line, Some(column)),
+            None => (fragment.strip_prefix('L')?, None),
+        };
+        let line: u32 = line.parse().ok()?;
+        let column: u32 = column.map_or(Some(1), |column| column.parse().ok())?;
+        let position = Position::new(line.saturating_sub(1), column.saturating_sub(1));
+        Some(Range::new(position, position))
+    });
+    url.set_fragment(None);
+    match url.to_file_path() {
+        Ok(path) => (file_path_to_uri(path), selection),
+        Err(()) => (target.clone(), None),
+    }
+}
+
+pub fn document_link_open(meta: EditorMeta, params: PositionParams, ctx: &mut Context) {
+    request_document_links(meta, ctx, move |ctx, meta, links| {
+        let Some(document) = ctx.documents.get(&meta.buffile) else {
+            return;
+        };
+        let Some(link) = links.into_iter().find(|(server_id, link)| {
+            let server = ctx.server(*server_id);
+            let range = lsp_range_to_kakoune(&link.range, &document.text, server.offset_encoding);
+            range.start <= params.position && params.position <= range.end
+        }) else {
+            ctx.show_error(meta, "lsp-document-link: no link at cursor");
+            return;
+        };
+        resolve_document_links(meta, vec![link], ctx, |ctx, meta, mut links| {
+            let Some((server_id, link)) = links.pop() else {
+                return;
+            };
+            let Some(target) = link.target else {
+                ctx.show_error(meta, "lsp-document-link: link has no target");
+                return;
+            };
+            let (uri, selection) = link_target_selection(&target);
+            open_document(
+                meta,
+                server_id,
+                ShowDocumentParams {
+                    uri,
+                    external: None,
+                    take_focus: Some(true),
+                    selection,
+                },
+                ctx,
+            );
+        });
+    });
+}
+
+pub fn document_links_list(meta: EditorMeta, ctx: &mut Context) {
+    request_document_links(meta, ctx, |ctx, meta, links| {
+        if links.is_empty() {
+            ctx.show_error(meta, "lsp-document-links: no links found");
+            return;
+        }
+        resolve_document_links(meta, links, ctx, editor_document_links_list);
+    });
+}
+
+fn editor_document_links_list(
+    ctx: &mut Context,
+    meta: EditorMeta,
+    links: Vec<(ServerId, DocumentLink)>,
+) {
+    let Some(document) = ctx.documents.get(&meta.buffile) else {
+        return;
+    };
+    let path = short_file_path(&meta.buffile, ctx.main_root(&meta));
+    let contents = links
+        .into_iter()
+        .map(|(server_id, link)| {
+            let server = ctx.server(server_id);
+            let range = lsp_range_to_kakoune(&link.range, &document.text, server.offset_encoding);
+            (range, link)
+        })
+        .sorted_by_key(|(range, _)| range.start)
+        .map(|(range, link)| {
+            let target = match &link.target {
+                Some(target)
+                    if target
+                        .scheme()
+                        .is_some_and(|scheme| scheme.as_str() == "file") =>
+                {
+                    let target_path = uri_to_file_path(target);
+                    short_file_path(target_path.to_str().unwrap(), ctx.main_root(&meta)).to_string()
+                }
+                Some(target) => target.to_string(),
+                None => "(no target)".to_string(),
+            };
+            let tooltip = link
+                .tooltip
+                .map(|tooltip| format!(" ({})", tooltip))
+                .unwrap_or_default();
+            format!(
+                "{}:{}:{}: {}{}\n",
+                path, range.start.line, range.start.column, target, tooltip
+            )
+        })
+        .join("");
+    let command = format!(
+        "lsp-show-goto-choices {} {}",
+        editor_quote(ctx.main_root(&meta)),
+        editor_quote(&contents),
+    );
+    ctx.exec(meta, command);
+}
+
+pub fn document_links_highlight(meta: EditorMeta, ctx: &mut Context) {
+    request_document_links(meta, ctx, |ctx, meta, links| {
+        let Some(document) = ctx.documents.get(&meta.buffile) else {
+            return;
+        };
+        let ranges = links
+            .into_iter()
+            .map(|(server_id, link)| {
+                let server = ctx.server(server_id);
+                let range =
+                    lsp_range_to_kakoune(&link.range, &document.text, server.offset_encoding);
+                editor_quote(&format!("{}|DocumentLink", ForwardKakouneRange(range)))
+            })
+            .join(" ");
+        let version = meta.version;
+        let command = format!("set-option buffer lsp_document_links {version} {ranges}");
+        let command = format!(
+            "evaluate-commands -buffer {} -verbatim -- {}",
+            editor_quote(&meta.buffile),
+            &command
+        );
+        ctx.exec(meta, command)
+    });
+}
@@ -0,0 +1,161 @@
+use crate::capabilities::{attempt_server_capability, CAPABILITY_FOLDING_RANGE};
+use crate::context::*;
+use crate::position::*;
+use crate::types::*;
+use crate::util::{editor_quote, file_path_to_uri};
+use itertools::Itertools;
+use lsp_types::request::*;
+use lsp_types::*;
+
+pub fn text_document_folding_range(
+    meta: EditorMeta,
+    params: FoldingRangeEditorParams,
+    ctx: &mut Context,
+) {
+    let eligible_servers: Vec<_> = ctx
+        .servers(&meta)
+        .filter(|srv| attempt_server_capability(ctx, *srv, &meta, CAPABILITY_FOLDING_RANGE))
+        .collect();
+    if eligible_servers.is_empty() {
+        return;
+    }
+
+    let req_params = eligible_servers
+        .into_iter()
+        .map(|(server_id, _)| {
+            (
+                server_id,
+                vec![FoldingRangeParams {
+                    text_document: TextDocumentIdentifier {
+                        uri: file_path_to_uri(&meta.buffile),
+                    },
+                    work_done_progress_params: Default::default(),
+                    partial_result_params: Default::default(),
+                }],
+            )
+        })
+        .collect();
+    ctx.call::<FoldingRangeRequest, _>(
+        meta,
+        RequestParams::Each(req_params),
+        move |ctx: &mut Context, meta, results| {
+            let result = match results
+                .into_iter()
+                .find(|(_, v)| v.as_ref().is_some_and(|v| !v.is_empty()))
+            {
+                Some(result) => result,
+                None => (meta.servers[0], None),
+            };
+
+            editor_folding_range(meta, params, result, ctx)
+        },
+    );
+}
+
+fn editor_folding_range(
+    meta: EditorMeta,
+    params: FoldingRangeEditorParams,
+    result: (ServerId, Option<Vec<FoldingRange>>),
+    ctx: &mut Context,
+) {
+    let (server_id, folding_ranges) = result;
+    let folding_ranges = folding_ranges.unwrap_or_default();
+    let Some(document) = ctx.documents.get(&meta.buffile) else {
+        return;
+    };
+    let server = ctx.server(server_id);
+
+    let mut folds: Vec<(KakouneRange, Option<FoldingRangeKind>)> = folding_ranges
+        .into_iter()
+        .map(|fold| {
+            let range = Range {
+                start: Position::new(fold.start_line, fold.start_character.unwrap_or(0)),
+                // Without an end character, the fold includes the whole end line.
+                end: match fold.end_character {
+                    Some(end_character) => Position::new(fold.end_line, end_character),
+                    None => Position::new(fold.end_line + 1, 0),
+                },
+            };
+            (
+                lsp_range_to_kakoune(&range, &document.text, server.offset_encoding),
+                fold.kind,
+            )
+        })
+        .collect();
+    folds.sort_by_key(|(range, _)| (range.start, std::cmp::Reverse(range.end)));
+
+    let folding_ranges = folds
+        .iter()
+        .map(|(range, kind)| {
+            let kind = match kind {
+                Some(FoldingRangeKind::Comment) => "comment",
+                Some(FoldingRangeKind::Imports) => "imports",
+                Some(FoldingRangeKind::Region) => "region",
+                None => "",
+            };
+            format!("{}|{}", ForwardKakouneRange(*range), kind)
+        })
+        .join(" ");
+    let mut command = format!(
+        "evaluate-commands -buffer {} -verbatim -- set-option buffer lsp_folding_ranges {} {}",
+        editor_quote(&meta.buffile),
+        meta.version,
+        folding_ranges
+    );
+
+    let cursor = params.position;
+    let containing = || {
+        folds
+            .iter()
+            .map(|(range, _)| *range)
+            .filter(|range| range.start <= cursor && cursor <= range.end)
+    };
+    let target = match params.action.as_str() {
+        "update" => None,
+        // Folds are sorted by start, and enclosing folds come first.
+        "innermost" => match containing().next_back() {
+            Some(range) => Some(range),
+            None => {
+                ctx.show_error(meta, "lsp-folding-range-select: no fold at cursor");
+                return;
+            }
+        },
+        "outermost" => match containing().next() {
+            Some(range) => Some(range),
+            None => {
+                ctx.show_error(meta, "lsp-folding-range-select: no fold at cursor");
+                return;
+            }
+        },
+        "next" | "previous" => {
+            let start = if params.action == "next" {
+                folds
+                    .iter()
+                    .map(|(range, _)| range.start)
+                    .find(|start| *start > cursor)
+            } else {
+                folds
+                    .iter()
+                    .rev()
+                    .map(|(range, _)| range.start)
+                    .find(|start| *start < cursor)
+            };
+            match start {
+                Some(start) => Some(KakouneRange { start, end: start }),
+                None => {
+                    let err = format!("lsp-{}-folding-range: no more folds", params.action);
+                    ctx.show_error(meta, err);
+                    return;
+                }
+            }
+        }
+        _ => {
+            ctx.show_error(meta, "lsp-folding-range: invalid action");
+            return;
+        }
+    };
+    if let Some(range) = target {
+        command.push_str(&format!("\nselect {}", ForwardKakouneRange(range)));
+    }
+    ctx.exec(meta, command);
+}
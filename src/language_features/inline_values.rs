@@ -0,0 +1,140 @@
+use std::collections::BTreeMap;
+
+use itertools::Itertools;
+use lsp_types::{
+    request::InlineValueRequest, InlineValue, InlineValueContext, InlineValueEvaluatableExpression,
+    InlineValueParams, InlineValueText, InlineValueVariableLookup, Position, Range,
+    TextDocumentIdentifier,
+};
+
+use crate::{
+    capabilities::{attempt_server_capability, CAPABILITY_INLINE_VALUES},
+    context::{Context, RequestParams},
+    markup::escape_kakoune_markup,
+    position::{get_line, kakoune_range_to_lsp, lsp_range_text, parse_kakoune_range},
+    types::{EditorMeta, ServerId},
+    util::{editor_quote, escape_tuple_element, file_path_to_uri},
+};
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct InlineValuesOptions {
+    pub frame_id: i32,
+    pub stopped_location: String,
+    pub buf_line_count: u32,
+}
+
+pub fn inline_values(meta: EditorMeta, params: InlineValuesOptions, ctx: &mut Context) {
+    let eligible_servers: Vec<_> = ctx
+        .servers(&meta)
+        .filter(|srv| attempt_server_capability(ctx, *srv, &meta, CAPABILITY_INLINE_VALUES))
+        .collect();
+    if eligible_servers.is_empty() {
+        return;
+    }
+    let Some(document) = ctx.documents.get(&meta.buffile) else {
+        return;
+    };
+    let (stopped_location, _) = parse_kakoune_range(&params.stopped_location);
+
+    let req_params = eligible_servers
+        .into_iter()
+        .map(|(server_id, server)| {
+            (
+                server_id,
+                vec![InlineValueParams {
+                    work_done_progress_params: Default::default(),
+                    text_document: TextDocumentIdentifier {
+                        uri: file_path_to_uri(&meta.buffile),
+                    },
+                    range: Range::new(Position::new(0, 0), Position::new(params.buf_line_count, 0)),
+                    context: InlineValueContext {
+                        frame_id: params.frame_id,
+                        stopped_location: kakoune_range_to_lsp(
+                            &stopped_location,
+                            &document.text,
+                            server.offset_encoding,
+                        ),
+                    },
+                }],
+            )
+        })
+        .collect();
+    ctx.call::<InlineValueRequest, _>(
+        meta,
+        RequestParams::Each(req_params),
+        move |ctx, meta, results| {
+            let results = results
+                .into_iter()
+                .flat_map(|(server_id, v)| {
+                    let v: Vec<_> = v
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|v| (server_id, v))
+                        .collect();
+                    v
+                })
+                .collect();
+            inline_values_response(meta, results, ctx)
+        },
+    );
+}
+
+fn inline_values_response(
+    meta: EditorMeta,
+    inline_values: Vec<(ServerId, InlineValue)>,
+    ctx: &mut Context,
+) {
+    let Some(document) = ctx.documents.get(&meta.buffile) else {
+        return;
+    };
+
+    // Like other editors, show all values of a line at its end.
+    let mut lines: BTreeMap<u32, Vec<String>> = BTreeMap::new();
+    for (server_id, inline_value) in inline_values {
+        let server = ctx.server(server_id);
+        let range_text = |range: &Range| {
+            lsp_range_text(range, &document.text, server.offset_encoding).unwrap_or_default()
+        };
+        let (range, label) = match inline_value {
+            InlineValue::Text(InlineValueText { range, text }) => (range, text),
+            // There is no debug adapter to evaluate these, so show what would be evaluated.
+            InlineValue::VariableLookup(InlineValueVariableLookup {
+                range,
+                variable_name,
+                ..
+            }) => (range, variable_name.unwrap_or_else(|| range_text(&range))),
+            InlineValue::EvaluatableExpression(InlineValueEvaluatableExpression {
+                range,
+                expression,
+            }) => (range, expression.unwrap_or_else(|| range_text(&range))),
+        };
+        lines.entry(range.start.line).or_default().push(label);
+    }
+
+    let ranges = lines
+        .into_iter()
+        .filter(|(line, _)| (*line as usize) < document.text.len_lines())
+        .map(|(line, labels)| {
+            let line_text = get_line(line as usize, &document.text);
+            let mut end_of_line = line_text.len_bytes();
+            if line_text.chars().last() == Some('\n') {
+                end_of_line -= 1;
+            }
+            let label = escape_tuple_element(&escape_kakoune_markup(&labels.join(", ")));
+            editor_quote(&format!(
+                "{}.{}+0| {{InlineValue}}{label}",
+                line + 1,
+                end_of_line + 1
+            ))
+        })
+        .join(" ");
+
+    let version = meta.version;
+    let command = format!("set-option buffer lsp_inline_values {version} {ranges}");
+    let command = format!(
+        "evaluate-commands -buffer {} -verbatim -- {}",
+        editor_quote(&meta.buffile),
+        &command
+    );
+    ctx.exec(meta, command)
+}
@@ -0,0 +1,116 @@
+use crate::capabilities::{attempt_server_capability, CAPABILITY_LINKED_EDITING_RANGE};
+use crate::context::*;
+use crate::position::*;
+use crate::types::*;
+use crate::util::file_path_to_uri;
+use itertools::Itertools;
+use lsp_types::request::LinkedEditingRange;
+use lsp_types::*;
+use regex::Regex;
+
+pub fn text_document_linked_editing_range(
+    meta: EditorMeta,
+    params: PositionParams,
+    ctx: &mut Context,
+) {
+    let eligible_servers: Vec<_> = ctx
+        .servers(&meta)
+        .filter(|srv| attempt_server_capability(ctx, *srv, &meta, CAPABILITY_LINKED_EDITING_RANGE))
+        .collect();
+    if eligible_servers.is_empty() {
+        return;
+    }
+
+    let req_params = eligible_servers
+        .into_iter()
+        .map(|(server_id, server_settings)| {
+            (
+                server_id,
+                vec![LinkedEditingRangeParams {
+                    text_document_position_params: TextDocumentPositionParams {
+                        text_document: TextDocumentIdentifier {
+                            uri: file_path_to_uri(&meta.buffile),
+                        },
+                        position: get_lsp_position(
+                            server_settings,
+                            &meta.buffile,
+                            &params.position,
+                            ctx,
+                        )
+                        .unwrap(),
+                    },
+                    work_done_progress_params: Default::default(),
+                }],
+            )
+        })
+        .collect();
+    ctx.call::<LinkedEditingRange, _>(
+        meta,
+        RequestParams::Each(req_params),
+        move |ctx: &mut Context, meta, results| {
+            let result = match results.into_iter().find(|(_, v)| v.is_some()) {
+                Some(result) => result,
+                None => (meta.servers[0], None),
+            };
+
+            editor_linked_editing_range(meta, params.position, result, ctx)
+        },
+    );
+}
+
+fn editor_linked_editing_range(
+    meta: EditorMeta,
+    main_cursor: KakounePosition,
+    result: (ServerId, Option<LinkedEditingRanges>),
+    ctx: &mut Context,
+) {
+    let (server_id, result) = result;
+    let Some(linked_ranges) = result else {
+        ctx.show_error(meta, "lsp-linked-editing-range: no linked ranges at cursor");
+        return;
+    };
+    let Some(document) = ctx.documents.get(&meta.buffile) else {
+        return;
+    };
+    let server = ctx.server(server_id);
+
+    // The word pattern describes valid contents of the linked ranges. A range that doesn't
+    // match is most likely stale, so don't select it.
+    let word_pattern = linked_ranges.word_pattern.as_ref().and_then(|pattern| {
+        match Regex::new(&format!("^(?:{})$", pattern)) {
+            Ok(word_pattern) => Some(word_pattern),
+            Err(err) => {
+                warn!(ctx.to_editor(), "invalid word pattern {}: {}", pattern, err);
+                None
+            }
+        }
+    });
+    let ranges: Vec<KakouneRange> = linked_ranges
+        .ranges
+        .iter()
+        .filter(|range| {
+            word_pattern.as_ref().map_or(true, |word_pattern| {
+                lsp_range_text(range, &document.text, server.offset_encoding)
+                    .is_some_and(|text| word_pattern.is_match(&text))
+            })
+        })
+        .map(|range| lsp_range_to_kakoune(range, &document.text, server.offset_encoding))
+        .collect();
+
+    let Some(main_range) = ranges
+        .iter()
+        .find(|range| range.start <= main_cursor && main_cursor <= range.end)
+    else {
+        ctx.show_error(meta, "lsp-linked-editing-range: no linked ranges at cursor");
+        return;
+    };
+    let command = format!(
+        "select {}",
+        Some(main_range)
+            .into_iter()
+            .chain(ranges.iter().filter(|range| *range != main_range))
+            .map(|range| format!("{}", ForwardKakouneRange(*range)))
+            .join(" ")
+    );
+    ctx.exec(meta, command);
+}
@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+
+use crate::capabilities::{attempt_server_capability, CAPABILITY_ON_TYPE_FORMATTING};
+use crate::context::*;
+use crate::position::*;
+use crate::text_edit::apply_text_edits_to_buffer;
+use crate::types::*;
+use crate::util::{editor_quote, escape_kakoune_regex, file_path_to_uri};
+use itertools::Itertools;
+use lsp_types::request::*;
+use lsp_types::*;
+
+fn trigger_characters(server: &ServerSettings) -> Vec<&str> {
+    let Some(options) = server
+        .capabilities
+        .as_ref()
+        .and_then(|caps| caps.document_on_type_formatting_provider.as_ref())
+    else {
+        return vec![];
+    };
+    Some(options.first_trigger_character.as_str())
+        .into_iter()
+        .chain(
+            options
+                .more_trigger_character
+                .iter()
+                .flatten()
+                .map(|ch| ch.as_str()),
+        )
+        .collect()
+}
+
+/// Installs an insert mode hook that requests formatting after typing one of the trigger
+/// characters of the buffer's servers.
+pub fn enable_on_type_formatting(meta: &EditorMeta, ctx: &Context) {
+    let filter = meta
+        .servers
+        .iter()
+        .flat_map(|server_id| trigger_characters(ctx.server(*server_id)))
+        .unique()
+        .map(escape_kakoune_regex)
+        .join("|");
+    if filter.is_empty() {
+        return;
+    }
+    let command = format!(
+        "evaluate-commands -buffer {} -verbatim -- lsp-on-type-formatting-enable {}",
+        editor_quote(&meta.buffile),
+        editor_quote(&filter),
+    );
+    ctx.exec(meta.clone(), command);
+}
+
+pub fn text_document_on_type_formatting(
+    meta: EditorMeta,
+    params: OnTypeFormattingParams,
+    ctx: &mut Context,
+) {
+    // Edits of multiple servers would conflict, so use the first one that is interested in
+    // this character.
+    let Some((server_id, server)) = ctx
+        .servers(&meta)
+        .filter(|server| {
+            attempt_server_capability(ctx, *server, &meta, CAPABILITY_ON_TYPE_FORMATTING)
+        })
+        .find(|(_, server)| trigger_characters(server).contains(&params.ch.as_str()))
+    else {
+        return;
+    };
+    let Some(position) = get_lsp_position(server, &meta.buffile, &params.position, ctx) else {
+        return;
+    };
+
+    let mut req_params = HashMap::new();
+    req_params.insert(
+        server_id,
+        vec![DocumentOnTypeFormattingParams {
+            text_document_position: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier {
+                    uri: file_path_to_uri(&meta.buffile),
+                },
+                position,
+            },
+            ch: params.ch,
+            options: params.formatting_options,
+        }],
+    );
+    ctx.call::<OnTypeFormatting, _>(
+        meta,
+        RequestParams::Each(req_params),
+        move |ctx, meta, mut results| {
+            let text_edits = results
+                .first_mut()
+                .and_then(|(_, v)| v.take())
+                .unwrap_or_default();
+            editor_on_type_formatting(meta, (server_id, text_edits), ctx)
+        },
+    );
+}
+
+fn editor_on_type_formatting(
+    meta: EditorMeta,
+    result: (ServerId, Vec<TextEdit>),
+    ctx: &mut Context,
+) {
+    let (server_id, text_edits) = result;
+    let Some(document) = ctx.documents.get(&meta.buffile) else {
+        return;
+    };
+    // The user probably kept typing, so the edits no longer apply.
+    if document.version != meta.version {
+        return;
+    }
+    let server = ctx.server(server_id);
+    // Since the edits are applied in a draft context, the selections of the client are kept.
+    let Some(cmd) = apply_text_edits_to_buffer(
+        ctx.to_editor(),
+        &meta.client,
+        None,
+        text_edits,
+        &document.text,
+        server.offset_encoding,
+        false,
+    ) else {
+        return;
+    };
+    ctx.exec(meta, cmd);
+}
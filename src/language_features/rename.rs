@@ -1,7 +1,8 @@
+use crate::capabilities::{attempt_server_capability, CAPABILITY_RENAME};
 use crate::context::*;
 use crate::position::*;
 use crate::types::*;
-use crate::util::file_path_to_uri;
+use crate::util::{editor_quote, file_path_to_uri};
 
 use lsp_types::request::*;
 use lsp_types::*;
@@ -42,17 +43,122 @@ pub fn text_document_rename(meta: EditorMeta, params: TextDocumentRenameParams,
                 None => (meta.servers[0], None),
             };
 
-            editor_rename(meta, result, ctx)
+            editor_rename(meta, result, params.preview, ctx)
         },
     );
 }
 
-// TODO handle version, so change is not applied if buffer is modified (and need to show a warning)
-fn editor_rename(meta: EditorMeta, result: (ServerId, Option<WorkspaceEdit>), ctx: &mut Context) {
+fn editor_rename(
+    meta: EditorMeta,
+    result: (ServerId, Option<WorkspaceEdit>),
+    preview: bool,
+    ctx: &mut Context,
+) {
     let (server_id, result) = result;
     if result.is_none() {
         return;
     }
     let result = result.unwrap();
-    workspace::apply_edit(server_id, meta, None, result, ctx);
+    if preview {
+        workspace::preview_edit(server_id, meta, None, result, ctx);
+    } else {
+        workspace::apply_edit(server_id, meta, None, result, ctx);
+    }
+}
+
+fn server_supports_prepare_rename(server: &ServerSettings) -> bool {
+    matches!(
+        server
+            .capabilities
+            .as_ref()
+            .and_then(|caps| caps.rename_provider.as_ref()),
+        Some(OneOf::Right(RenameOptions {
+            prepare_provider: Some(true),
+            ..
+        }))
+    )
+}
+
+/// Checks that the symbol at the cursor can be renamed before prompting for a new name.
+pub fn text_document_prepare_rename(meta: EditorMeta, params: PositionParams, ctx: &mut Context) {
+    let eligible_servers: Vec<_> = ctx
+        .servers(&meta)
+        .filter(|srv| attempt_server_capability(ctx, *srv, &meta, CAPABILITY_RENAME))
+        .filter(|(_, server)| server_supports_prepare_rename(server))
+        .collect();
+    if eligible_servers.is_empty() {
+        // Fall back to guessing the identifier at the cursor.
+        ctx.exec(meta, "lsp-rename-prompt-identifier");
+        return;
+    }
+
+    let req_params = eligible_servers
+        .into_iter()
+        .map(|(server_id, server_settings)| {
+            (
+                server_id,
+                vec![TextDocumentPositionParams {
+                    text_document: TextDocumentIdentifier {
+                        uri: file_path_to_uri(&meta.buffile),
+                    },
+                    position: get_lsp_position(
+                        server_settings,
+                        &meta.buffile,
+                        &params.position,
+                        ctx,
+                    )
+                    .unwrap(),
+                }],
+            )
+        })
+        .collect();
+    ctx.call::<PrepareRenameRequest, _>(
+        meta,
+        RequestParams::Each(req_params),
+        move |ctx: &mut Context, meta, results| {
+            let result = match results.into_iter().find(|(_, v)| v.is_some()) {
+                Some(result) => result,
+                None => (meta.servers[0], None),
+            };
+
+            editor_prepare_rename(meta, result, ctx)
+        },
+    );
+}
+
+fn editor_prepare_rename(
+    meta: EditorMeta,
+    result: (ServerId, Option<PrepareRenameResponse>),
+    ctx: &mut Context,
+) {
+    let (server_id, result) = result;
+    let range = match result {
+        None => {
+            ctx.show_error(
+                meta,
+                "lsp-rename: the symbol at the cursor cannot be renamed",
+            );
+            return;
+        }
+        Some(PrepareRenameResponse::RangeWithPlaceholder { placeholder, .. }) => {
+            let command = format!("lsp-rename-prompt-init {}", editor_quote(&placeholder));
+            ctx.exec(meta, command);
+            return;
+        }
+        Some(PrepareRenameResponse::DefaultBehavior { .. }) => {
+            ctx.exec(meta, "lsp-rename-prompt-identifier");
+            return;
+        }
+        Some(PrepareRenameResponse::Range(range)) => range,
+    };
+    let Some(document) = ctx.documents.get(&meta.buffile) else {
+        return;
+    };
+    let server = ctx.server(server_id);
+    let Some(text) = lsp_range_text(&range, &document.text, server.offset_encoding) else {
+        ctx.show_error(meta, "lsp-rename: invalid range returned by prepareRename");
+        return;
+    };
+    let command = format!("lsp-rename-prompt-init {}", editor_quote(&text));
+    ctx.exec(meta, command);
 }
@@ -1,15 +1,21 @@
+use std::collections::HashMap;
+
 use crate::capabilities::{attempt_server_capability, CAPABILITY_SEMANTIC_TOKENS};
-use crate::context::{Context, RequestParams};
+use crate::context::{Context, RequestParams, ServerSettings};
 use crate::position::lsp_range_to_kakoune;
 use crate::semantic_tokens_config;
-use crate::types::{EditorMeta, ForwardKakouneRange, ServerId};
+use crate::types::{EditorMeta, ForwardKakouneRange, SemanticTokensRangeEditorParams, ServerId};
 use crate::util::editor_quote;
 use crate::util::file_path_to_uri;
-use lsp_types::request::SemanticTokensFullRequest;
+use lsp_types::request::{
+    SemanticTokensFullDeltaRequest, SemanticTokensFullRequest, SemanticTokensRangeRequest,
+};
 use lsp_types::{
-    Position, Range, SemanticToken, SemanticTokenModifier, SemanticTokensOptions,
-    SemanticTokensParams, SemanticTokensRegistrationOptions, SemanticTokensResult,
-    SemanticTokensServerCapabilities::*, TextDocumentIdentifier,
+    Position, Range, SemanticToken, SemanticTokenModifier, SemanticTokens,
+    SemanticTokensDeltaParams, SemanticTokensEdit, SemanticTokensFullDeltaResult,
+    SemanticTokensFullOptions, SemanticTokensOptions, SemanticTokensParams,
+    SemanticTokensRangeParams, SemanticTokensRangeResult, SemanticTokensRegistrationOptions,
+    SemanticTokensResult, SemanticTokensServerCapabilities::*, TextDocumentIdentifier,
 };
 
 pub fn tokens_request(meta: EditorMeta, ctx: &mut Context) {
@@ -21,46 +27,272 @@ pub fn tokens_request(meta: EditorMeta, ctx: &mut Context) {
         return;
     }
 
-    let (first_server, _) = *eligible_servers.first().unwrap();
-    let first_server = first_server.to_owned();
+    let text_document = TextDocumentIdentifier {
+        uri: file_path_to_uri(&meta.buffile),
+    };
+    let mut full_params = HashMap::new();
+    let mut delta_params: HashMap<ServerId, Vec<SemanticTokensDeltaParams>> = HashMap::new();
+    for (server_id, server) in eligible_servers {
+        let previous_result_id = ctx
+            .semantic_tokens
+            .get(&(server_id, meta.buffile.clone()))
+            .and_then(|(result_id, _)| result_id.clone())
+            .filter(|_| supports_delta(server));
+        match previous_result_id {
+            Some(previous_result_id) => {
+                delta_params.insert(
+                    server_id,
+                    vec![SemanticTokensDeltaParams {
+                        work_done_progress_params: Default::default(),
+                        partial_result_params: Default::default(),
+                        text_document: text_document.clone(),
+                        previous_result_id,
+                    }],
+                );
+            }
+            None => {
+                full_params.insert(
+                    server_id,
+                    vec![SemanticTokensParams {
+                        partial_result_params: Default::default(),
+                        text_document: text_document.clone(),
+                        work_done_progress_params: Default::default(),
+                    }],
+                );
+            }
+        }
+    }
+
+    if !full_params.is_empty() {
+        ctx.call::<SemanticTokensFullRequest, _>(
+            meta.clone(),
+            RequestParams::Each(full_params),
+            move |ctx, meta, results| {
+                for (server_id, response) in results {
+                    let Some(response) = response else {
+                        continue;
+                    };
+                    let tokens = match response {
+                        SemanticTokensResult::Tokens(tokens) => tokens,
+                        SemanticTokensResult::Partial(partial) => SemanticTokens {
+                            result_id: None,
+                            data: partial.data,
+                        },
+                    };
+                    ctx.semantic_tokens.insert(
+                        (server_id, meta.buffile.clone()),
+                        (tokens.result_id, tokens.data),
+                    );
+                }
+                render_stored_tokens(meta, ctx);
+            },
+        );
+    }
+    if !delta_params.is_empty() {
+        let previous_result_ids: HashMap<ServerId, String> = delta_params
+            .iter()
+            .map(|(server_id, params)| (*server_id, params[0].previous_result_id.clone()))
+            .collect();
+        ctx.call::<SemanticTokensFullDeltaRequest, _>(
+            meta,
+            RequestParams::Each(delta_params),
+            move |ctx, meta, results| {
+                for (server_id, response) in results {
+                    let key = (server_id, meta.buffile.clone());
+                    let Some(response) = response else {
+                        ctx.semantic_tokens.remove(&key);
+                        continue;
+                    };
+                    let (result_id, edits) = match response {
+                        SemanticTokensFullDeltaResult::Tokens(tokens) => {
+                            ctx.semantic_tokens
+                                .insert(key, (tokens.result_id, tokens.data));
+                            continue;
+                        }
+                        SemanticTokensFullDeltaResult::TokensDelta(delta) => {
+                            (delta.result_id, delta.edits)
+                        }
+                        SemanticTokensFullDeltaResult::PartialTokensDelta { edits } => {
+                            (None, edits)
+                        }
+                    };
+                    // Another response may have replaced the tokens the delta applies to.
+                    let Some((Some(stored_result_id), _)) = ctx.semantic_tokens.get(&key) else {
+                        continue;
+                    };
+                    if *stored_result_id != previous_result_ids[&server_id] {
+                        continue;
+                    }
+                    let (_, data) = ctx.semantic_tokens.remove(&key).unwrap();
+                    match apply_semantic_tokens_edits(data, edits) {
+                        Some(data) => {
+                            ctx.semantic_tokens.insert(key, (result_id, data));
+                        }
+                        None => warn!(
+                            ctx.to_editor(),
+                            "Invalid semantic tokens delta from {}",
+                            &ctx.server(server_id).name
+                        ),
+                    }
+                }
+                render_stored_tokens(meta, ctx);
+            },
+        );
+    }
+}
+
+/// Request semantic tokens only for the given lines, typically the visible part of a buffer.
+/// Falls back to requesting tokens for the whole buffer if no server supports range requests.
+pub fn tokens_range_request(
+    meta: EditorMeta,
+    params: SemanticTokensRangeEditorParams,
+    ctx: &mut Context,
+) {
+    let eligible_servers: Vec<_> = ctx
+        .servers(&meta)
+        .filter(|srv| attempt_server_capability(ctx, *srv, &meta, CAPABILITY_SEMANTIC_TOKENS))
+        .filter(|(_, server)| supports_range(server))
+        .collect();
+    if eligible_servers.is_empty() {
+        tokens_request(meta, ctx);
+        return;
+    }
 
+    let range = Range {
+        start: Position::new(params.first_line, 0),
+        end: Position::new(params.first_line + params.line_count, 0),
+    };
     let req_params = eligible_servers
         .into_iter()
         .map(|(server_id, _)| {
             (
                 server_id,
-                vec![SemanticTokensParams {
+                vec![SemanticTokensRangeParams {
+                    work_done_progress_params: Default::default(),
                     partial_result_params: Default::default(),
                     text_document: TextDocumentIdentifier {
                         uri: file_path_to_uri(&meta.buffile),
                     },
-                    work_done_progress_params: Default::default(),
+                    range,
                 }],
             )
         })
         .collect();
-    ctx.call::<SemanticTokensFullRequest, _>(
+    ctx.call::<SemanticTokensRangeRequest, _>(
         meta,
         RequestParams::Each(req_params),
         move |ctx, meta, results| {
-            let (server_id, response) = match results.into_iter().find(|(_, v)| v.is_some()) {
-                Some(result) => result,
-                None => (first_server, None),
+            let Some((server_id, response)) = results
+                .into_iter()
+                .find_map(|(server_id, v)| Some((server_id, v?)))
+            else {
+                return;
             };
-
-            if let Some(response) = response {
-                tokens_response(meta, (server_id, response), ctx);
-            }
+            let tokens = match response {
+                SemanticTokensRangeResult::Tokens(tokens) => tokens.data,
+                SemanticTokensRangeResult::Partial(partial) => partial.data,
+            };
+            tokens_response(meta, server_id, &tokens, ctx);
         },
     );
 }
 
-pub fn tokens_response(
+fn supports_delta(server: &ServerSettings) -> bool {
+    semantic_tokens_options(server).is_some_and(|options| {
+        matches!(
+            options.full,
+            Some(SemanticTokensFullOptions::Delta { delta: Some(true) })
+        )
+    })
+}
+
+fn supports_range(server: &ServerSettings) -> bool {
+    semantic_tokens_options(server).is_some_and(|options| options.range == Some(true))
+}
+
+fn semantic_tokens_options(server: &ServerSettings) -> Option<&SemanticTokensOptions> {
+    match server
+        .capabilities
+        .as_ref()?
+        .semantic_tokens_provider
+        .as_ref()?
+    {
+        SemanticTokensOptions(options) => Some(options),
+        SemanticTokensRegistrationOptions(options) => Some(&options.semantic_tokens_options),
+    }
+}
+
+/// Apply delta edits, which address the flat integer encoding of the tokens.
+fn apply_semantic_tokens_edits(
+    data: Vec<SemanticToken>,
+    mut edits: Vec<SemanticTokensEdit>,
+) -> Option<Vec<SemanticToken>> {
+    let mut ints: Vec<u32> = data
+        .into_iter()
+        .flat_map(|token| {
+            [
+                token.delta_line,
+                token.delta_start,
+                token.length,
+                token.token_type,
+                token.token_modifiers_bitset,
+            ]
+        })
+        .collect();
+    // Edits refer to the original array, so apply them back to front.
+    edits.sort_by_key(|edit| std::cmp::Reverse(edit.start));
+    for edit in edits {
+        let start = edit.start as usize;
+        let end = start.checked_add(edit.delete_count as usize)?;
+        if end > ints.len() {
+            return None;
+        }
+        let inserted = edit.data.unwrap_or_default().into_iter().flat_map(|token| {
+            [
+                token.delta_line,
+                token.delta_start,
+                token.length,
+                token.token_type,
+                token.token_modifiers_bitset,
+            ]
+        });
+        ints.splice(start..end, inserted);
+    }
+    if ints.len() % 5 != 0 {
+        return None;
+    }
+    Some(
+        ints.chunks_exact(5)
+            .map(|chunk| SemanticToken {
+                delta_line: chunk[0],
+                delta_start: chunk[1],
+                length: chunk[2],
+                token_type: chunk[3],
+                token_modifiers_bitset: chunk[4],
+            })
+            .collect(),
+    )
+}
+
+/// Render the tokens of the first server that has sent any.
+fn render_stored_tokens(meta: EditorMeta, ctx: &mut Context) {
+    let Some(server_id) = meta.servers.iter().copied().find(|&server_id| {
+        ctx.semantic_tokens
+            .contains_key(&(server_id, meta.buffile.clone()))
+    }) else {
+        return;
+    };
+    let (_, tokens) = &ctx.semantic_tokens[&(server_id, meta.buffile.clone())];
+    let tokens = tokens.clone();
+    tokens_response(meta, server_id, &tokens, ctx);
+}
+
+fn tokens_response(
     meta: EditorMeta,
-    response: (ServerId, SemanticTokensResult),
+    server_id: ServerId,
+    tokens: &[SemanticToken],
     ctx: &mut Context,
 ) {
-    let (server_id, tokens) = response;
     let server = ctx.server(server_id);
     let legend = match server
         .capabilities
@@ -79,16 +311,12 @@ pub fn tokens_response(
         Some(document) => document,
         None => return,
     };
-    let tokens = match tokens {
-        SemanticTokensResult::Tokens(tokens) => tokens.data,
-        SemanticTokensResult::Partial(partial) => partial.data,
-    };
     let mut line = 0;
     let mut start = 0;
     let ranges = tokens
-        .into_iter()
+        .iter()
         .filter_map(
-            |SemanticToken {
+            |&SemanticToken {
                  delta_line,
                  delta_start,
                  length,
@@ -155,3 +383,59 @@ pub fn tokens_response(
     );
     ctx.exec(meta, command)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(delta_line: u32, delta_start: u32, length: u32) -> SemanticToken {
+        SemanticToken {
+            delta_line,
+            delta_start,
+            length,
+            token_type: 0,
+            token_modifiers_bitset: 0,
+        }
+    }
+
+    #[test]
+    pub fn apply_semantic_tokens_edits_relative_to_original() {
+        let data = vec![token(0, 0, 3), token(1, 2, 4), token(1, 0, 5)];
+        let edits = vec![
+            SemanticTokensEdit {
+                start: 0,
+                delete_count: 5,
+                data: None,
+            },
+            SemanticTokensEdit {
+                start: 12,
+                delete_count: 1,
+                data: None,
+            },
+            SemanticTokensEdit {
+                start: 10,
+                delete_count: 0,
+                data: Some(vec![token(2, 1, 1)]),
+            },
+        ];
+        // The last edit only deletes part of a token.
+        assert_eq!(apply_semantic_tokens_edits(data.clone(), edits), None);
+
+        let edits = vec![
+            SemanticTokensEdit {
+                start: 10,
+                delete_count: 0,
+                data: Some(vec![token(2, 1, 1)]),
+            },
+            SemanticTokensEdit {
+                start: 0,
+                delete_count: 5,
+                data: None,
+            },
+        ];
+        assert_eq!(
+            apply_semantic_tokens_edits(data, edits),
+            Some(vec![token(1, 2, 4), token(2, 1, 1), token(1, 0, 5)])
+        );
+    }
+}
@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+
+use crate::context::*;
+use crate::language_features::call_hierarchy::format_location;
+use crate::position::*;
+use crate::types::*;
+use crate::util::*;
+use itertools::Itertools;
+use lsp_types::{request::*, *};
+
+pub fn type_hierarchy_prepare(meta: EditorMeta, params: TypeHierarchyParams, ctx: &mut Context) {
+    // lsp-types does not expose the typeHierarchyProvider capability, so we ask all servers.
+    // Those that don't support the method fail without showing an error.
+    let req_params = ctx
+        .servers(&meta)
+        .map(|(server_id, server_settings)| {
+            let position =
+                get_lsp_position(server_settings, &meta.buffile, &params.position, ctx).unwrap();
+            let uri = file_path_to_uri(&meta.buffile);
+            (
+                server_id,
+                vec![TypeHierarchyPrepareParams {
+                    text_document_position_params: TextDocumentPositionParams {
+                        text_document: TextDocumentIdentifier::new(uri),
+                        position,
+                    },
+                    work_done_progress_params: WorkDoneProgressParams::default(),
+                }],
+            )
+        })
+        .collect();
+
+    ctx.call::<TypeHierarchyPrepare, _>(
+        meta,
+        RequestParams::Each(req_params),
+        move |ctx: &mut Context, meta, results| {
+            request_type_hierarchy(meta, ctx, params.supertypes_or_subtypes, results);
+        },
+    )
+}
+
+fn request_type_hierarchy(
+    meta: EditorMeta,
+    ctx: &mut Context,
+    supertypes_or_subtypes: bool,
+    results: Vec<(ServerId, Option<Vec<TypeHierarchyItem>>)>,
+) {
+    let Some((server_id, item)) = results.into_iter().find_map(|(server_id, items)| {
+        items
+            .and_then(|items| items.into_iter().next())
+            .map(|item| (server_id, item))
+    }) else {
+        ctx.show_error(meta, "lsp-type-hierarchy: no type at cursor");
+        return;
+    };
+
+    if supertypes_or_subtypes {
+        let mut params = HashMap::new();
+        params.insert(
+            server_id,
+            vec![TypeHierarchySupertypesParams {
+                item: item.clone(),
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: PartialResultParams::default(),
+            }],
+        );
+
+        ctx.call::<TypeHierarchySupertypes, _>(
+            meta,
+            RequestParams::Each(params),
+            move |ctx: &mut Context, meta, results| {
+                if let Some(result) = results.first() {
+                    format_type_hierarchy(meta, ctx, supertypes_or_subtypes, &item, result);
+                }
+            },
+        );
+    } else {
+        let mut params = HashMap::new();
+        params.insert(
+            server_id,
+            vec![TypeHierarchySubtypesParams {
+                item: item.clone(),
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: PartialResultParams::default(),
+            }],
+        );
+
+        ctx.call::<TypeHierarchySubtypes, _>(
+            meta,
+            RequestParams::Each(params),
+            move |ctx: &mut Context, meta, results| {
+                if let Some(result) = results.first() {
+                    format_type_hierarchy(meta, ctx, supertypes_or_subtypes, &item, result);
+                }
+            },
+        );
+    }
+}
+
+fn format_type_hierarchy(
+    meta: EditorMeta,
+    ctx: &mut Context,
+    supertypes_or_subtypes: bool,
+    item: &TypeHierarchyItem,
+    result: &(ServerId, Option<Vec<TypeHierarchyItem>>),
+) {
+    let (server_id, result) = result;
+    let server_id = *server_id;
+    let result = match result {
+        Some(result) => result,
+        None => return,
+    };
+
+    let first_line_suffix = format!(
+        "{} - list of {}",
+        &item.name,
+        if supertypes_or_subtypes {
+            "supertypes"
+        } else {
+            "subtypes"
+        },
+    );
+
+    let contents = format_location(
+        server_id,
+        &meta,
+        ctx,
+        &item.uri,
+        item.selection_range.start,
+        "",
+        &first_line_suffix,
+    ) + &result
+        .iter()
+        .map(|other| {
+            let label = match &other.detail {
+                Some(detail) if !detail.is_empty() => format!("{} ({})", other.name, detail),
+                _ => other.name.clone(),
+            };
+            format_location(
+                server_id,
+                &meta,
+                ctx,
+                &other.uri,
+                other.selection_range.start,
+                "  ",
+                &label,
+            )
+        })
+        .join("");
+
+    let command = if supertypes_or_subtypes {
+        "lsp-show-supertypes"
+    } else {
+        "lsp-show-subtypes"
+    };
+    let command = format!(
+        "{} {} {}",
+        command,
+        editor_quote(ctx.main_root(&meta)),
+        editor_quote(&contents),
+    );
+    ctx.exec(meta, command);
+}
@@ -131,6 +131,40 @@ pub fn start(
     })
 }
 
+/// A transport whose messages to the language server are forwarded to the returned receiver.
+#[cfg(test)]
+pub fn mock(to_editor: ToEditorSender) -> (LanguageServerTransport, Receiver<ServerMessage>) {
+    let (forward, messages) = crossbeam_channel::unbounded();
+    let to_lang_server = Worker::spawn(
+        to_editor.clone(),
+        "Messages to language server",
+        1024,
+        move |_, receiver, _| {
+            for msg in receiver {
+                _ = forward.send(msg);
+            }
+        },
+    );
+    let from_lang_server = Worker::spawn(
+        to_editor.clone(),
+        "Messages from language server",
+        1,
+        |_, receiver: Receiver<Void>, _| for _ in receiver {},
+    );
+    let errors = Worker::spawn(
+        to_editor,
+        "Language server errors",
+        1,
+        |_, receiver: Receiver<Void>, _| for _ in receiver {},
+    );
+    let transport = LanguageServerTransport {
+        to_lang_server,
+        from_lang_server,
+        _errors: errors,
+    };
+    (transport, messages)
+}
+
 fn reader_loop(
     to_editor: &ToEditorSender,
     server_name: ServerName,
@@ -165,6 +165,27 @@ pub fn get_line(line_number: usize, text: &Rope) -> RopeSlice<'_> {
     text.line(min(line_number, text.len_lines() - 1))
 }
 
+/// Returns the text covered by an LSP range, or None if the range is out-of-bounds.
+pub fn lsp_range_text(
+    range: &Range,
+    text: &Rope,
+    offset_encoding: OffsetEncoding,
+) -> Option<String> {
+    let char_index = |position: Position| {
+        let line_number = position.line as usize;
+        if line_number >= text.len_lines() {
+            return None;
+        }
+        let line = text.line(line_number);
+        let byte_offset =
+            lsp_character_to_byte_offset(line, position.character as usize, offset_encoding)?;
+        Some(text.line_to_char(line_number) + line.byte_to_char(byte_offset))
+    };
+    let start = char_index(range.start)?;
+    let end = char_index(range.end)?;
+    (start <= end).then(|| text.slice(start..end).to_string())
+}
+
 /// Parse a range of the form <line1>.<column1>,<line2>.<column2>.
 /// The range is normalized, so the lower coordinate comes first.
 /// Returns the cursor position separately.
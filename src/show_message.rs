@@ -1,13 +1,19 @@
 use std::borrow::Cow;
 
 use itertools::Itertools;
+use jsonrpc_core::Params;
 use jsonrpc_core::{Id, MethodCall};
-use lsp_types::{MessageActionItem, MessageType, ShowMessageRequestParams};
+use lsp_types::{
+    MessageActionItem, MessageType, ShowDocumentParams, ShowDocumentResult,
+    ShowMessageRequestParams,
+};
 use serde::Deserialize;
 
 use crate::{
     context::Context,
     editor_transport::ToEditorSender,
+    language_features::goto::edit_at_range,
+    position::{get_file_contents, lsp_range_to_kakoune},
     types::{EditorMeta, ServerId},
     util::editor_quote,
     LAST_CLIENT,
@@ -145,6 +151,68 @@ pub fn show_message(
     );
 }
 
+/// Implements ShowDocument request.
+pub fn show_document(
+    server_id: ServerId,
+    params: Params,
+    ctx: &mut Context,
+) -> Result<serde_json::Value, jsonrpc_core::Error> {
+    let params: ShowDocumentParams = params
+        .parse()
+        .expect("Failed to parse ShowDocumentParams params");
+    // Server requests are not tied to a client, so use the one the user interacted with last.
+    let meta = LAST_CLIENT
+        .lock()
+        .unwrap()
+        .clone()
+        .map(EditorMeta::for_client)
+        .unwrap_or_default();
+    let success = open_document(meta, server_id, params, ctx);
+    Ok(serde_json::to_value(ShowDocumentResult { success }).unwrap())
+}
+
+/// Opens a file in the jump client, or passes other URIs to the lsp_uri_opener.
+/// Returns false if the file cannot be read.
+pub fn open_document(
+    meta: EditorMeta,
+    server_id: ServerId,
+    params: ShowDocumentParams,
+    ctx: &mut Context,
+) -> bool {
+    let path = url::Url::parse(params.uri.as_str())
+        .ok()
+        .filter(|url| url.scheme() == "file")
+        .and_then(|url| url.to_file_path().ok());
+    let command = match path {
+        Some(path) if params.external != Some(true) => {
+            let path = path.to_str().unwrap();
+            let Some(contents) = get_file_contents(path, ctx) else {
+                ctx.show_error(meta, format!("cannot open {}", path));
+                return false;
+            };
+            let mut command = match params.selection {
+                Some(selection) => {
+                    let server = ctx.server(server_id);
+                    let range = lsp_range_to_kakoune(&selection, &contents, server.offset_encoding);
+                    edit_at_range(path, range, true)
+                }
+                None => format!("edit -existing -- {}", editor_quote(path)),
+            };
+            if params.take_focus == Some(true) {
+                // "focus" is provided by the windowing modules, which may not be loaded.
+                command.push_str("\ntry focus");
+            }
+            format!(
+                "evaluate-commands -try-client %opt{{jumpclient}} -- {}",
+                editor_quote(&command)
+            )
+        }
+        _ => format!("lsp-open-uri {}", editor_quote(params.uri.as_str())),
+    };
+    ctx.exec(meta, command);
+    true
+}
+
 fn update_modeline(meta: EditorMeta, ctx: &Context) {
     let modeline = if ctx.pending_message_requests.is_empty() {
         Cow::from("")
@@ -10,7 +10,7 @@ use indoc::indoc;
 use itertools::Itertools;
 use lsp_types::notification::DidOpenTextDocument;
 use lsp_types::*;
-use ropey::Rope;
+use ropey::{Rope, RopeSlice};
 use std::borrow::Cow;
 use std::fs::{self, File};
 use std::io::{BufReader, Write};
@@ -194,7 +194,7 @@ pub fn apply_text_edits_to_file<T: TextEditish<T>>(
     }
 }
 
-fn apply_text_edits_to_rope<T: TextEditish<T>>(
+pub fn apply_text_edits_to_rope<T: TextEditish<T>>(
     text: Rope,
     text_edits: Vec<T>,
     offset_encoding: OffsetEncoding,
@@ -595,11 +595,138 @@ fn minimal_edit_sequence<T: TextEditish<T>>(old: &Rope, new: &Rope) -> Vec<T> {
     builder.edits
 }
 
+/// Renders the changes from old to new as a unified diff with three lines of context.
+pub fn unified_diff(old_label: &str, new_label: &str, old: &Rope, new: &Rope) -> String {
+    const CONTEXT: usize = 3;
+    let oldv = old.lines().collect::<Vec<_>>();
+    let newv = new.lines().collect::<Vec<_>>();
+    // Ropey yields an empty last line after a trailing newline.
+    let old_len = oldv.len() - usize::from(oldv.last().is_some_and(|l| l.len_chars() == 0));
+    let new_len = newv.len() - usize::from(newv.last().is_some_and(|l| l.len_chars() == 0));
+
+    enum Line {
+        Context(usize, usize),
+        Delete(usize, usize),
+        Insert(usize, usize),
+    }
+    struct BuildDiffLines {
+        lines: Vec<Line>,
+    }
+    impl diffs::Diff for BuildDiffLines {
+        type Error = ();
+        fn equal(&mut self, o: usize, n: usize, len: usize) -> Result<(), ()> {
+            self.lines
+                .extend((0..len).map(|i| Line::Context(o + i, n + i)));
+            Ok(())
+        }
+        fn delete(&mut self, o: usize, len: usize, n: usize) -> Result<(), ()> {
+            self.lines.extend((0..len).map(|i| Line::Delete(o + i, n)));
+            Ok(())
+        }
+        fn insert(&mut self, o: usize, n: usize, new_len: usize) -> Result<(), ()> {
+            self.lines
+                .extend((0..new_len).map(|i| Line::Insert(o, n + i)));
+            Ok(())
+        }
+    }
+    let mut builder = BuildDiffLines { lines: vec![] };
+    let _result = diffs::myers::diff(&mut builder, &oldv, 0, old_len, &newv, 0, new_len);
+    let lines = builder.lines;
+
+    let changes: Vec<usize> = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !matches!(line, Line::Context(..)))
+        .map(|(i, _)| i)
+        .collect();
+    if changes.is_empty() {
+        return String::new();
+    }
+
+    // Merge changes whose context would overlap into the same hunk.
+    let mut hunks: Vec<(usize, usize)> = vec![];
+    for &i in &changes {
+        let start = i.saturating_sub(CONTEXT);
+        let end = (i + CONTEXT + 1).min(lines.len());
+        match hunks.last_mut() {
+            Some((_, last_end)) if start <= *last_end => *last_end = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let with_newline = |line: RopeSlice| {
+        let mut line = line.to_string();
+        if !line.ends_with('\n') {
+            line.push('\n');
+        }
+        line
+    };
+    let mut diff = format!("--- {old_label}\n+++ {new_label}\n");
+    for (start, end) in hunks {
+        let (old_start, new_start) = match lines[start] {
+            Line::Context(o, n) | Line::Delete(o, n) | Line::Insert(o, n) => (o, n),
+        };
+        let hunk = &lines[start..end];
+        let old_count = hunk
+            .iter()
+            .filter(|line| !matches!(line, Line::Insert(..)))
+            .count();
+        let new_count = hunk
+            .iter()
+            .filter(|line| !matches!(line, Line::Delete(..)))
+            .count();
+        // Empty ranges refer to the line before.
+        let position = |start: usize, count: usize| if count == 0 { start } else { start + 1 };
+        diff.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            position(old_start, old_count),
+            old_count,
+            position(new_start, new_count),
+            new_count
+        ));
+        for line in hunk {
+            match *line {
+                Line::Context(o, _) => diff.push_str(&format!(" {}", with_newline(oldv[o]))),
+                Line::Delete(o, _) => diff.push_str(&format!("-{}", with_newline(oldv[o]))),
+                Line::Insert(_, n) => diff.push_str(&format!("+{}", with_newline(newv[n]))),
+            }
+        }
+    }
+    diff
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::editor_transport::mock_to_editor;
 
+    #[test]
+    fn unified_diff_hunks() {
+        let old = Rope::from_str("a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n");
+        let new = Rope::from_str("a\nB\nc\nd\ne\nf\ng\nh\ni\nj\nk\n");
+        assert_eq!(
+            unified_diff("a/file", "b/file", &old, &new),
+            indoc!(
+                "--- a/file
+                 +++ b/file
+                 @@ -1,5 +1,5 @@
+                  a
+                 -b
+                 +B
+                  c
+                  d
+                  e
+                 @@ -8,3 +8,4 @@
+                  h
+                  i
+                  j
+                 +k
+                 "
+            )
+        );
+        assert_eq!(unified_diff("a", "b", &old, &old), "");
+    }
+
     fn edit(
         start_line: u32,
         start_character: u32,
@@ -4,10 +4,13 @@ use std::{
     time::Duration,
 };
 
+use crate::diagnostics::text_document_diagnostic;
+use crate::language_features::on_type_formatting::enable_on_type_formatting;
 use crate::thread_worker::Worker;
 use crate::types::*;
 use crate::util::file_path_to_uri;
 use crate::util::uri_to_file_path;
+use crate::workspace::remove_unused_workspace_folders;
 use crate::{context::*, editor_transport::ToEditorSender};
 use crossbeam_channel::{Receiver, Sender};
 use jsonrpc_core::Value;
@@ -43,6 +46,8 @@ pub fn text_document_did_open(
     for &server_id in &meta.servers {
         ctx.notify::<DidOpenTextDocument>(server_id, params.clone());
     }
+    enable_on_type_formatting(&meta, ctx);
+    text_document_diagnostic(meta, ctx);
 }
 
 pub fn text_document_did_change(
@@ -60,33 +65,121 @@ pub fn text_document_did_change(
     if old_version >= version {
         return;
     }
+    let text = Rope::from_str(&params.draft);
     let document = Document {
         version,
-        text: Rope::from_str(&params.draft),
+        text: text.clone(),
     };
 
     // Resets metadata for buffer.
-    ctx.documents.insert(meta.buffile.clone(), document);
+    let old_document = ctx.documents.insert(meta.buffile.clone(), document);
     ctx.diagnostics.insert(meta.buffile.clone(), Vec::new());
 
-    let req_params = DidChangeTextDocumentParams {
-        text_document: VersionedTextDocumentIdentifier {
-            uri,
-            version: meta.version,
-        },
-        content_changes: vec![TextDocumentContentChangeEvent {
-            range: None,
-            range_length: None,
-            text: params.draft,
-        }],
+    let text_document = VersionedTextDocumentIdentifier {
+        uri,
+        version: meta.version,
     };
     for &server_id in &meta.servers {
-        ctx.notify::<DidChangeTextDocument>(server_id, req_params.clone());
+        let server = ctx.server(server_id);
+        let content_change = match &old_document {
+            Some(old_document) if server_supports_incremental_sync(server) => {
+                incremental_content_change(&old_document.text, &text, server.offset_encoding)
+            }
+            _ => TextDocumentContentChangeEvent {
+                range: None,
+                range_length: None,
+                text: params.draft.clone(),
+            },
+        };
+        let req_params = DidChangeTextDocumentParams {
+            text_document: text_document.clone(),
+            content_changes: vec![content_change],
+        };
+        ctx.notify::<DidChangeTextDocument>(server_id, req_params);
+    }
+    text_document_diagnostic(meta, ctx);
+}
+
+fn server_supports_incremental_sync(server: &ServerSettings) -> bool {
+    let sync_kind = match &server.capabilities.as_ref().unwrap().text_document_sync {
+        Some(TextDocumentSyncCapability::Kind(kind)) => *kind,
+        Some(TextDocumentSyncCapability::Options(TextDocumentSyncOptions {
+            change: Some(kind),
+            ..
+        })) => *kind,
+        _ => return false,
+    };
+    sync_kind == TextDocumentSyncKind::INCREMENTAL
+}
+
+/// Compute a single change event that turns the old text into the new one, by replacing
+/// everything between the longest common prefix and the longest common suffix.
+fn incremental_content_change(
+    old: &Rope,
+    new: &Rope,
+    offset_encoding: OffsetEncoding,
+) -> TextDocumentContentChangeEvent {
+    let mut prefix = old
+        .chars()
+        .zip(new.chars())
+        .take_while(|(a, b)| a == b)
+        .count();
+    // Don't split a CRLF line ending.
+    if prefix > 0
+        && prefix < old.len_chars()
+        && old.char(prefix - 1) == '\r'
+        && old.char(prefix) == '\n'
+    {
+        prefix -= 1;
+    }
+    let max_suffix = old.len_chars().min(new.len_chars()) - prefix;
+    let mut suffix = old
+        .chars_at(old.len_chars())
+        .reversed()
+        .zip(new.chars_at(new.len_chars()).reversed())
+        .take(max_suffix)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_end = old.len_chars() - suffix;
+    if suffix > 0 && old_end > 0 && old.char(old_end - 1) == '\r' && old.char(old_end) == '\n' {
+        suffix -= 1;
+    }
+    let old_end = old.len_chars() - suffix;
+    let new_end = new.len_chars() - suffix;
+    TextDocumentContentChangeEvent {
+        range: Some(Range {
+            start: char_index_to_lsp_position(old, prefix, offset_encoding),
+            end: char_index_to_lsp_position(old, old_end, offset_encoding),
+        }),
+        range_length: None,
+        text: new.slice(prefix..new_end).to_string(),
+    }
+}
+
+fn char_index_to_lsp_position(
+    text: &Rope,
+    char_idx: usize,
+    offset_encoding: OffsetEncoding,
+) -> Position {
+    let line_idx = text.char_to_line(char_idx);
+    let line = text.line(line_idx);
+    let char_in_line = char_idx - text.line_to_char(line_idx);
+    let character = match offset_encoding {
+        OffsetEncoding::Utf8 => line.char_to_byte(char_in_line),
+        OffsetEncoding::Utf16 => line.char_to_utf16_cu(char_in_line),
+    };
+    Position {
+        line: line_idx as _,
+        character: character as _,
     }
 }
 
 pub fn text_document_did_close(meta: EditorMeta, ctx: &mut Context) {
     ctx.documents.remove(&meta.buffile);
+    ctx.pulled_diagnostics
+        .retain(|(_, buffile), _| buffile != &meta.buffile);
+    ctx.semantic_tokens
+        .retain(|(_, buffile), _| buffile != &meta.buffile);
     let uri = file_path_to_uri(&meta.buffile);
     let params = DidCloseTextDocumentParams {
         text_document: TextDocumentIdentifier { uri },
@@ -94,6 +187,7 @@ pub fn text_document_did_close(meta: EditorMeta, ctx: &mut Context) {
     for &server_id in &meta.servers {
         ctx.notify::<DidCloseTextDocument>(server_id, params.clone());
     }
+    remove_unused_workspace_folders(&meta, ctx);
 }
 
 pub fn text_document_did_save(meta: EditorMeta, ctx: &mut Context) {
@@ -316,3 +410,94 @@ pub fn register_workspace_did_change_watched_files(
             .push(CompiledFileSystemWatcher { kind, pattern });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply_change(old: &str, change: &TextDocumentContentChangeEvent) -> String {
+        let range = change.range.unwrap();
+        let text = Rope::from_str(old);
+        let start = text.line_to_char(range.start.line as _)
+            + text
+                .line(range.start.line as _)
+                .utf16_cu_to_char(range.start.character as _);
+        let end = text.line_to_char(range.end.line as _)
+            + text
+                .line(range.end.line as _)
+                .utf16_cu_to_char(range.end.character as _);
+        let mut text = text;
+        text.remove(start..end);
+        text.insert(start, &change.text);
+        text.to_string()
+    }
+
+    #[test]
+    pub fn incremental_content_change_insert_line() {
+        let old = "fn main() {\n}\n";
+        let new = "fn main() {\n    println!();\n}\n";
+        let change = incremental_content_change(
+            &Rope::from_str(old),
+            &Rope::from_str(new),
+            OffsetEncoding::Utf16,
+        );
+        assert_eq!(
+            change.range,
+            Some(Range {
+                start: Position {
+                    line: 1,
+                    character: 0
+                },
+                end: Position {
+                    line: 1,
+                    character: 0
+                },
+            })
+        );
+        assert_eq!(change.text, "    println!();\n");
+        assert_eq!(apply_change(old, &change), new);
+    }
+
+    #[test]
+    pub fn incremental_content_change_unicode() {
+        let old = "let s = \"§𝕏a\";\n";
+        let new = "let s = \"§𝕏b\";\n";
+        let change = incremental_content_change(
+            &Rope::from_str(old),
+            &Rope::from_str(new),
+            OffsetEncoding::Utf16,
+        );
+        assert_eq!(
+            change.range,
+            Some(Range {
+                start: Position {
+                    line: 0,
+                    character: 12
+                },
+                end: Position {
+                    line: 0,
+                    character: 13
+                },
+            })
+        );
+        assert_eq!(apply_change(old, &change), new);
+        let change = incremental_content_change(
+            &Rope::from_str(old),
+            &Rope::from_str(new),
+            OffsetEncoding::Utf8,
+        );
+        assert_eq!(change.range.unwrap().start.character, 15);
+    }
+
+    #[test]
+    pub fn incremental_content_change_crlf() {
+        let old = "a\r\nb\r\n";
+        let new = "a\r\r\nb\r\n";
+        let change = incremental_content_change(
+            &Rope::from_str(old),
+            &Rope::from_str(new),
+            OffsetEncoding::Utf16,
+        );
+        assert_eq!(apply_change(old, &change), new);
+    }
+}
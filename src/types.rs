@@ -392,6 +392,9 @@ pub type ServerId = usize;
 #[derive(Debug)]
 pub struct EditorCompletion {
     pub offset: u32,
+    pub prefer_replace: bool,
+    /// Replacements for the names of completion item kinds in the menu, by kind name.
+    pub kind_icons: HashMap<String, String>,
 }
 
 #[derive(Debug)]
@@ -435,6 +438,12 @@ pub struct CallHierarchyParams {
     pub incoming_or_outgoing: bool,
 }
 
+#[derive(Clone, Debug)]
+pub struct TypeHierarchyParams {
+    pub position: KakounePosition,
+    pub supertypes_or_subtypes: bool,
+}
+
 #[derive(Clone, Debug)]
 pub enum CodeActionFilter {
     ByKind(Vec<CodeActionKind>),
@@ -449,17 +458,64 @@ pub struct CodeActionsParams {
     pub filters: Option<CodeActionFilter>,
 }
 
+#[derive(Clone, Debug)]
+pub struct CodeActionsFixAllParams {
+    /// Switches like "-code E0308" that restrict which diagnostics are fixed.
+    pub filters: Vec<String>,
+}
+
 #[derive(Clone, Debug)]
 pub struct CodeActionResolveParams {
     pub code_action: String,
 }
 
+#[derive(Clone, Debug)]
+pub struct OnTypeFormattingParams {
+    pub position: KakounePosition,
+    pub ch: String,
+    pub formatting_options: FormattingOptions,
+}
+
 #[derive(Clone, Debug)]
 pub struct RangeFormattingParams {
     pub formatting_options: FormattingOptions,
     pub ranges: Vec<String>,
 }
 
+#[derive(Clone, Copy, Debug)]
+pub enum DiagnosticsExportFormat {
+    Json,
+    Sarif,
+    /// One "file:line:column: severity: message" line per diagnostic.
+    Quickfix,
+}
+
+#[derive(Clone, Debug)]
+pub struct DiagnosticsExportParams {
+    /// Export the diagnostics of all files instead of only the current buffer's.
+    pub session: bool,
+    pub format: DiagnosticsExportFormat,
+    /// File or FIFO to write to.
+    pub path: String,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct DiagnosticRelatedInformationParams {
+    pub position: KakounePosition,
+    /// Also highlight the related ranges in their buffers.
+    pub highlight: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct NextOrPrevDiagnosticParams {
+    pub position: KakounePosition,
+    /// If true then searches forward ("next")
+    /// otherwise searches backward ("previous")
+    pub search_next: bool,
+    /// Switches like "-severity error" that restrict which diagnostics are visited.
+    pub filters: Vec<String>,
+}
+
 #[derive(Clone, Debug)]
 pub struct NextOrPrevSymbolParams {
     pub position: KakounePosition,
@@ -500,6 +556,7 @@ pub struct ObjectParams {
 pub struct TextDocumentRenameParams {
     pub position: KakounePosition,
     pub new_name: String,
+    pub preview: bool,
 }
 
 #[derive(Clone, Debug)]
@@ -510,6 +567,20 @@ pub struct SelectionRangePositionParams {
     pub selections_desc: Vec<String>,
 }
 
+#[derive(Clone, Debug)]
+pub struct FoldingRangeEditorParams {
+    pub position: KakounePosition,
+    // One of "update", "innermost", "outermost", "next" or "previous".
+    pub action: String,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SemanticTokensRangeEditorParams {
+    // The first visible line, 0-based.
+    pub first_line: u32,
+    pub line_count: u32,
+}
+
 // Language Server
 
 // XXX serde(untagged) ?
@@ -84,6 +84,22 @@ pub fn escape_tuple_element(s: &str) -> String {
     s.replace('\\', "\\\\").replace('|', "\\|")
 }
 
+/// Escape text so a Kakoune regex matches it literally.
+/// Only syntax characters are escaped: Kakoune rejects unknown escapes, and reads some of them,
+/// like `\<` and `\>`, as assertions.
+pub fn escape_kakoune_regex(s: &str) -> String {
+    s.chars()
+        .map(|c| match c {
+            '\n' => "\\n".to_string(),
+            '\t' => "\\t".to_string(),
+            '^' | '$' | '\\' | '.' | '*' | '+' | '?' | '(' | ')' | '[' | ']' | '{' | '}' | '|' => {
+                format!("\\{}", c)
+            }
+            c => c.to_string(),
+        })
+        .collect()
+}
+
 /// Convert language filetypes configuration into a more lookup-friendly form.
 pub fn filetype_to_language_id_map(
     config: &Config,
@@ -154,3 +170,17 @@ pub fn uri_to_file_path(uri: &Uri) -> PathBuf {
         .to_file_path()
         .unwrap()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_kakoune_regex_only_escapes_syntax_characters() {
+        assert_eq!(escape_kakoune_regex("<"), "<");
+        assert_eq!(escape_kakoune_regex(">"), ">");
+        assert_eq!(escape_kakoune_regex("\\"), "\\\\");
+        assert_eq!(escape_kakoune_regex("::"), "::");
+        assert_eq!(escape_kakoune_regex("a.b|c\n"), "a\\.b\\|c\\n");
+    }
+}
@@ -1,19 +1,26 @@
 use crate::context::*;
 use crate::controller::can_serve;
 use crate::language_features::{document_symbol, rust_analyzer};
+use crate::position::get_file_contents;
 use crate::settings::*;
-use crate::text_edit::apply_text_edits_try_deferred;
+use crate::text_edit::{
+    apply_text_edits_to_rope, apply_text_edits_try_deferred, unified_diff, TextEditish,
+};
 use crate::types::*;
 use crate::util::uri_to_file_path;
 use crate::util::*;
-use jsonrpc_core::Params;
+use itertools::Itertools;
+use jsonrpc_core::{MethodCall, Params};
 use lsp_types::notification::*;
 use lsp_types::request::*;
 use lsp_types::*;
+use ropey::Rope;
 use serde::Deserialize;
 use serde_json::{self, Value};
+use std::collections::HashMap;
 use std::fs;
 use std::io;
+use std::path::{Path, PathBuf};
 
 #[derive(Clone, Deserialize, Debug)]
 pub struct EditorDidChangeConfigurationParams {
@@ -58,6 +65,76 @@ pub fn did_change_configuration(
     }
 }
 
+/// Whether the server can be told about workspace folders added after initialization.
+pub fn supports_workspace_folder_changes(server: &ServerSettings) -> bool {
+    server
+        .capabilities
+        .as_ref()
+        .and_then(|caps| caps.workspace.as_ref())
+        .and_then(|ws| ws.workspace_folders.as_ref())
+        .is_some_and(|wsf| {
+            wsf.supported == Some(true)
+                && matches!(
+                    wsf.change_notifications,
+                    Some(OneOf::Left(true)) | Some(OneOf::Right(_))
+                )
+        })
+}
+
+fn workspace_folder(root: &RootPath) -> WorkspaceFolder {
+    WorkspaceFolder {
+        uri: file_path_to_uri(root),
+        name: root.clone(),
+    }
+}
+
+pub fn did_change_workspace_folders(
+    server_id: ServerId,
+    added: &[RootPath],
+    removed: &[RootPath],
+    ctx: &mut Context,
+) {
+    let params = DidChangeWorkspaceFoldersParams {
+        event: WorkspaceFoldersChangeEvent {
+            added: added.iter().map(workspace_folder).collect(),
+            removed: removed.iter().map(workspace_folder).collect(),
+        },
+    };
+    ctx.notify::<DidChangeWorkspaceFolders>(server_id, params);
+}
+
+/// Removes workspace folders that no longer contain any open buffer, except for the one
+/// the server was started with.
+pub fn remove_unused_workspace_folders(meta: &EditorMeta, ctx: &mut Context) {
+    for &server_id in &meta.servers {
+        let server = ctx.server(server_id);
+        if !supports_workspace_folder_changes(server) {
+            continue;
+        }
+        let unused: Vec<RootPath> = server
+            .roots
+            .iter()
+            .skip(1)
+            .filter(|root| {
+                !ctx.documents
+                    .keys()
+                    .any(|buffile| Path::new(buffile).starts_with(root))
+            })
+            .cloned()
+            .collect();
+        if unused.is_empty() {
+            continue;
+        }
+        let server = ctx.language_servers.get_mut(&server_id).unwrap();
+        server.roots.retain(|root| !unused.contains(root));
+        let server_name = server.name.clone();
+        for root in &unused {
+            ctx.route_cache.remove(&(server_name.clone(), root.clone()));
+        }
+        did_change_workspace_folders(server_id, &[], &unused, ctx);
+    }
+}
+
 pub fn configuration(
     meta: EditorMeta,
     params: Params,
@@ -319,14 +396,96 @@ pub fn apply_document_resource_op(op: ResourceOp) -> io::Result<()> {
     }
 }
 
-// TODO handle version, so change is not applied if buffer is modified (and need to show a warning)
+/// Returns an error if a document changed since the server computed the edit.
+fn check_document_versions(edit: &WorkspaceEdit, ctx: &Context) -> Result<(), String> {
+    let text_document_edits: Vec<&TextDocumentEdit> = match &edit.document_changes {
+        Some(DocumentChanges::Edits(edits)) => edits.iter().collect(),
+        Some(DocumentChanges::Operations(ops)) => ops
+            .iter()
+            .filter_map(|op| match op {
+                DocumentChangeOperation::Edit(edit) => Some(edit),
+                DocumentChangeOperation::Op(_) => None,
+            })
+            .collect(),
+        None => vec![],
+    };
+    for edit in text_document_edits {
+        let Some(version) = edit.text_document.version else {
+            continue;
+        };
+        let path = uri_to_file_path(&edit.text_document.uri);
+        let Some(document) = ctx.documents.get(path.to_str().unwrap()) else {
+            continue;
+        };
+        if document.version != version {
+            return Err(format!(
+                "{} was modified (version {}) since the edit was computed (version {})",
+                path.display(),
+                document.version,
+                version
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Applies a workspace edit, unless some of its changes need confirmation by the user, in which
+/// case they are shown in a preview first.
 pub fn apply_edit(
     server_id: ServerId,
     meta: EditorMeta,
     response_fifo: Option<ResponseFifo>,
     edit: WorkspaceEdit,
     ctx: &mut Context,
+) {
+    apply_edit_then(server_id, meta, response_fifo, edit, ctx, |_, _, _| ())
+}
+
+/// Like apply_edit, but passes the outcome to the callback, which may only be known once the
+/// user has accepted or rejected the preview.
+pub fn apply_edit_then(
+    server_id: ServerId,
+    meta: EditorMeta,
+    response_fifo: Option<ResponseFifo>,
+    edit: WorkspaceEdit,
+    ctx: &mut Context,
+    callback: impl FnOnce(&mut Context, EditorMeta, ApplyWorkspaceEditResponse) + 'static,
+) {
+    if needs_confirmation(&edit) {
+        start_preview(
+            server_id,
+            meta,
+            response_fifo,
+            edit,
+            Some(Box::new(callback)),
+            ctx,
+        );
+        return;
+    }
+    let response = apply_confirmed_edit(server_id, meta.clone(), response_fifo, edit, ctx);
+    callback(ctx, meta, response);
+}
+
+fn not_applied(reason: impl Into<String>) -> ApplyWorkspaceEditResponse {
+    ApplyWorkspaceEditResponse {
+        applied: false,
+        failure_reason: Some(reason.into()),
+        failed_change: None,
+    }
+}
+
+fn apply_confirmed_edit(
+    server_id: ServerId,
+    meta: EditorMeta,
+    response_fifo: Option<ResponseFifo>,
+    edit: WorkspaceEdit,
+    ctx: &mut Context,
 ) -> ApplyWorkspaceEditResponse {
+    if let Err(reason) = check_document_versions(&edit, ctx) {
+        let msg = format!("refusing to apply workspace edit: {}", reason);
+        ctx.show_error_fifo(meta, response_fifo, &msg);
+        return not_applied(msg);
+    }
     let mut command = String::new();
     if let Some(document_changes) = edit.document_changes {
         match document_changes {
@@ -356,6 +515,7 @@ pub fn apply_edit(
                             );
                         }
                         DocumentChangeOperation::Op(op) => {
+                            let file_event = FileOperationEvent::of(&op);
                             if let Err(e) = apply_document_resource_op(op) {
                                 error!(
                                     ctx.to_editor(),
@@ -367,6 +527,7 @@ pub fn apply_edit(
                                     failed_change: None,
                                 };
                             }
+                            did_file_operation(file_event, ctx);
                         }
                     }
                 }
@@ -387,6 +548,380 @@ pub fn apply_edit(
     }
 }
 
+/// Changes that share a change annotation label, shown and selected together.
+#[derive(Clone, Debug)]
+pub struct ChangeGroup {
+    /// None for changes without annotation.
+    pub label: Option<String>,
+    pub description: Option<String>,
+    pub needs_confirmation: bool,
+    pub selected: bool,
+}
+
+pub type EditAppliedCallback =
+    Box<dyn FnOnce(&mut Context, EditorMeta, ApplyWorkspaceEditResponse)>;
+
+pub struct PendingWorkspaceEdit {
+    pub server_id: ServerId,
+    pub edit: WorkspaceEdit,
+    pub groups: Vec<ChangeGroup>,
+    /// Runs once the edit has been accepted and applied, or rejected.
+    pub on_applied: Option<EditAppliedCallback>,
+}
+
+fn resource_op_annotation_id(op: &ResourceOp) -> Option<&ChangeAnnotationIdentifier> {
+    match op {
+        ResourceOp::Create(op) => op.annotation_id.as_ref(),
+        ResourceOp::Rename(op) => op.annotation_id.as_ref(),
+        ResourceOp::Delete(op) => op
+            .options
+            .as_ref()
+            .and_then(|options| options.annotation_id.as_ref()),
+    }
+}
+
+fn text_edit_annotation_id(
+    edit: &OneOf<TextEdit, AnnotatedTextEdit>,
+) -> Option<&ChangeAnnotationIdentifier> {
+    match edit {
+        OneOf::Left(_) => None,
+        OneOf::Right(edit) => Some(&edit.annotation_id),
+    }
+}
+
+/// Returns the annotation ids of all changes in order, None for changes without annotation.
+fn change_annotation_ids(edit: &WorkspaceEdit) -> Vec<Option<&ChangeAnnotationIdentifier>> {
+    fn text_document_edit_ids(edit: &TextDocumentEdit) -> Vec<Option<&ChangeAnnotationIdentifier>> {
+        edit.edits.iter().map(text_edit_annotation_id).collect()
+    }
+    match &edit.document_changes {
+        Some(DocumentChanges::Edits(edits)) => {
+            edits.iter().flat_map(text_document_edit_ids).collect()
+        }
+        Some(DocumentChanges::Operations(ops)) => ops
+            .iter()
+            .flat_map(|op| match op {
+                DocumentChangeOperation::Edit(edit) => text_document_edit_ids(edit),
+                DocumentChangeOperation::Op(op) => vec![resource_op_annotation_id(op)],
+            })
+            .collect(),
+        None => match &edit.changes {
+            Some(changes) if changes.values().any(|edits| !edits.is_empty()) => vec![None],
+            _ => vec![],
+        },
+    }
+}
+
+fn change_annotation<'a>(
+    edit: &'a WorkspaceEdit,
+    id: Option<&ChangeAnnotationIdentifier>,
+) -> Option<&'a ChangeAnnotation> {
+    edit.change_annotations.as_ref()?.get(id?)
+}
+
+fn needs_confirmation(edit: &WorkspaceEdit) -> bool {
+    change_annotation_ids(edit).into_iter().any(|id| {
+        change_annotation(edit, id)
+            .is_some_and(|annotation| annotation.needs_confirmation == Some(true))
+    })
+}
+
+/// Groups the changes by annotation label. Groups that need confirmation start deselected.
+fn change_groups(edit: &WorkspaceEdit) -> Vec<ChangeGroup> {
+    let mut groups: Vec<ChangeGroup> = vec![];
+    for id in change_annotation_ids(edit) {
+        let annotation = change_annotation(edit, id);
+        let label = annotation.map(|annotation| annotation.label.clone());
+        let needs_confirmation =
+            annotation.is_some_and(|annotation| annotation.needs_confirmation == Some(true));
+        match groups.iter_mut().find(|group| group.label == label) {
+            Some(group) => {
+                group.needs_confirmation |= needs_confirmation;
+                group.selected &= !needs_confirmation;
+            }
+            None => groups.push(ChangeGroup {
+                label,
+                description: annotation.and_then(|annotation| annotation.description.clone()),
+                needs_confirmation,
+                selected: !needs_confirmation,
+            }),
+        }
+    }
+    groups
+}
+
+/// Returns the part of a workspace edit whose changes belong to one of the given groups.
+fn select_changes(edit: &WorkspaceEdit, labels: &[Option<&str>]) -> WorkspaceEdit {
+    let is_selected = |id: Option<&ChangeAnnotationIdentifier>| {
+        let label = change_annotation(edit, id).map(|annotation| annotation.label.as_str());
+        labels.contains(&label)
+    };
+    let select_text_document_edit = |text_document_edit: &TextDocumentEdit| {
+        let edits: Vec<_> = text_document_edit
+            .edits
+            .iter()
+            .filter(|edit| is_selected(text_edit_annotation_id(edit)))
+            .cloned()
+            .collect();
+        (!edits.is_empty()).then(|| TextDocumentEdit {
+            text_document: text_document_edit.text_document.clone(),
+            edits,
+        })
+    };
+    let document_changes = edit.document_changes.as_ref().map(|changes| match changes {
+        DocumentChanges::Edits(edits) => {
+            DocumentChanges::Edits(edits.iter().filter_map(select_text_document_edit).collect())
+        }
+        DocumentChanges::Operations(ops) => DocumentChanges::Operations(
+            ops.iter()
+                .filter_map(|op| match op {
+                    DocumentChangeOperation::Edit(edit) => {
+                        select_text_document_edit(edit).map(DocumentChangeOperation::Edit)
+                    }
+                    DocumentChangeOperation::Op(op) => is_selected(resource_op_annotation_id(op))
+                        .then(|| DocumentChangeOperation::Op(op.clone())),
+                })
+                .collect(),
+        ),
+    });
+    WorkspaceEdit {
+        changes: edit.changes.clone().filter(|_| is_selected(None)),
+        document_changes,
+        change_annotations: edit.change_annotations.clone(),
+    }
+}
+
+/// Shows the changes of a workspace edit as a unified diff, to be accepted or rejected.
+/// If the changes carry annotations, they are grouped by label, and groups can be deselected.
+pub fn preview_edit(
+    server_id: ServerId,
+    meta: EditorMeta,
+    response_fifo: Option<ResponseFifo>,
+    edit: WorkspaceEdit,
+    ctx: &mut Context,
+) {
+    start_preview(server_id, meta, response_fifo, edit, None, ctx);
+}
+
+fn start_preview(
+    server_id: ServerId,
+    meta: EditorMeta,
+    response_fifo: Option<ResponseFifo>,
+    edit: WorkspaceEdit,
+    on_applied: Option<EditAppliedCallback>,
+    ctx: &mut Context,
+) {
+    // Only one preview is shown at a time, so a second one must not replace the pending one.
+    let refusal = if ctx.pending_workspace_edit.is_some() {
+        Err("another workspace edit is waiting for confirmation, accept or reject it first".into())
+    } else {
+        check_document_versions(&edit, ctx)
+    };
+    if let Err(reason) = refusal {
+        let msg = format!("refusing to preview workspace edit: {}", reason);
+        ctx.show_error_fifo(meta.clone(), response_fifo, &msg);
+        if let Some(on_applied) = on_applied {
+            on_applied(ctx, meta, not_applied(msg));
+        }
+        return;
+    }
+    let groups = change_groups(&edit);
+    ctx.pending_workspace_edit = Some(PendingWorkspaceEdit {
+        server_id,
+        edit,
+        groups,
+        on_applied,
+    });
+    show_pending_edit(meta, response_fifo, ctx);
+}
+
+fn show_pending_edit(meta: EditorMeta, response_fifo: Option<ResponseFifo>, ctx: &mut Context) {
+    let Some(pending) = ctx.pending_workspace_edit.as_ref() else {
+        return;
+    };
+    let contents = if pending.groups.iter().all(|group| group.label.is_none()) {
+        workspace_edit_diff(pending.server_id, &meta, &pending.edit, ctx)
+    } else {
+        let mut contents =
+            "# Change groups, toggle them with lsp-workspace-edit-toggle <number>:\n".to_string();
+        for (i, group) in pending.groups.iter().enumerate() {
+            contents.push_str(&format!(
+                "# {} [{}] {}{}{}\n",
+                i + 1,
+                if group.selected { "x" } else { " " },
+                group.label.as_deref().unwrap_or("Other changes"),
+                group
+                    .description
+                    .as_ref()
+                    .map(|description| format!(": {}", description))
+                    .unwrap_or_default(),
+                if group.needs_confirmation {
+                    " (needs confirmation)"
+                } else {
+                    ""
+                },
+            ));
+        }
+        for (i, group) in pending.groups.iter().enumerate() {
+            let edit = select_changes(&pending.edit, &[group.label.as_deref()]);
+            contents.push_str(&format!(
+                "\n# {} {}\n",
+                i + 1,
+                group.label.as_deref().unwrap_or("Other changes")
+            ));
+            contents.push_str(&workspace_edit_diff(pending.server_id, &meta, &edit, ctx));
+        }
+        contents
+    };
+
+    let command = format!(
+        "lsp-show-workspace-edit-preview {} {}",
+        editor_quote(&meta.buffile),
+        editor_quote(&contents),
+    );
+    ctx.exec_fifo(meta, response_fifo, command);
+}
+
+/// Renders the resource operations and text edits of a workspace edit.
+fn workspace_edit_diff(
+    server_id: ServerId,
+    meta: &EditorMeta,
+    edit: &WorkspaceEdit,
+    ctx: &Context,
+) -> String {
+    let mut file_edits: Vec<(Uri, Vec<TextEdit>)> = vec![];
+    let mut add_file_edits = |uri: &Uri, edits: Vec<TextEdit>| match file_edits
+        .iter_mut()
+        .find(|(other, _)| other == uri)
+    {
+        Some((_, file_edits)) => file_edits.extend(edits),
+        None => file_edits.push((uri.clone(), edits)),
+    };
+    let mut resource_ops = vec![];
+    match &edit.document_changes {
+        Some(DocumentChanges::Edits(edits)) => {
+            for edit in edits {
+                let edits = edit.edits.iter().cloned().map(|e| e.text_edit()).collect();
+                add_file_edits(&edit.text_document.uri, edits);
+            }
+        }
+        Some(DocumentChanges::Operations(ops)) => {
+            for op in ops {
+                match op {
+                    DocumentChangeOperation::Edit(edit) => {
+                        let edits = edit.edits.iter().cloned().map(|e| e.text_edit()).collect();
+                        add_file_edits(&edit.text_document.uri, edits);
+                    }
+                    DocumentChangeOperation::Op(op) => resource_ops.push(op.clone()),
+                }
+            }
+        }
+        None => {
+            if let Some(changes) = &edit.changes {
+                for (uri, edits) in changes
+                    .iter()
+                    .sorted_by_key(|(uri, _)| uri_to_file_path(uri))
+                {
+                    add_file_edits(uri, edits.clone());
+                }
+            }
+        }
+    }
+
+    let root = ctx.main_root(meta).clone();
+    let short_path =
+        |uri: &Uri| short_file_path(uri_to_file_path(uri).to_str().unwrap(), &root).to_string();
+    let mut contents = String::new();
+    for op in &resource_ops {
+        contents.push_str(&match op {
+            ResourceOp::Create(op) => format!("# create {}\n", short_path(&op.uri)),
+            ResourceOp::Rename(op) => format!(
+                "# rename {} to {}\n",
+                short_path(&op.old_uri),
+                short_path(&op.new_uri)
+            ),
+            ResourceOp::Delete(op) => format!("# delete {}\n", short_path(&op.uri)),
+        });
+    }
+    let server = ctx.server(server_id);
+    for (uri, mut edits) in file_edits {
+        let path = uri_to_file_path(&uri);
+        let label = short_path(&uri);
+        let Some(old) = get_file_contents(path.to_str().unwrap(), ctx) else {
+            contents.push_str(&format!("# cannot read {}\n", label));
+            continue;
+        };
+        edits.sort_by_key(|edit| edit.range.start);
+        let new = match apply_text_edits_to_rope(old.clone(), edits, server.offset_encoding) {
+            Ok(new) => Rope::from_str(&String::from_utf8_lossy(&new)),
+            Err(err) => {
+                contents.push_str(&format!("# cannot edit {}: {}\n", label, err));
+                continue;
+            }
+        };
+        contents.push_str(&unified_diff(&label, &label, &old, &new));
+    }
+    contents
+}
+
+#[derive(Clone, Debug)]
+pub struct EditorWorkspaceEditPreviewResponse {
+    pub accept: bool,
+}
+
+pub fn workspace_edit_preview_respond(
+    meta: EditorMeta,
+    params: EditorWorkspaceEditPreviewResponse,
+    ctx: &mut Context,
+) {
+    let Some(pending) = ctx.pending_workspace_edit.take() else {
+        ctx.show_error(meta, "no pending workspace edit");
+        return;
+    };
+    let response = if params.accept {
+        let labels: Vec<_> = pending
+            .groups
+            .iter()
+            .filter(|group| group.selected)
+            .map(|group| group.label.as_deref())
+            .collect();
+        let edit = select_changes(&pending.edit, &labels);
+        apply_confirmed_edit(pending.server_id, meta.clone(), None, edit, ctx)
+    } else {
+        not_applied("rejected by the user")
+    };
+    if let Some(on_applied) = pending.on_applied {
+        on_applied(ctx, meta, response);
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct EditorWorkspaceEditPreviewToggle {
+    pub group: usize,
+}
+
+pub fn workspace_edit_preview_toggle(
+    meta: EditorMeta,
+    params: EditorWorkspaceEditPreviewToggle,
+    ctx: &mut Context,
+) {
+    let Some(pending) = ctx.pending_workspace_edit.as_mut() else {
+        ctx.show_error(meta, "no pending workspace edit");
+        return;
+    };
+    let Some(group) = params
+        .group
+        .checked_sub(1)
+        .and_then(|i| pending.groups.get_mut(i))
+    else {
+        let msg = format!("no change group {}", params.group);
+        ctx.show_error(meta, msg);
+        return;
+    };
+    group.selected = !group.selected;
+    show_pending_edit(meta, None, ctx);
+}
+
 #[derive(Deserialize)]
 pub struct EditorApplyEdit {
     pub edit: String,
@@ -405,13 +940,558 @@ pub fn apply_edit_from_editor(
     apply_edit(server_id, meta, response_fifo, edit, ctx);
 }
 
+/// Applies an edit requested by a server. The response is deferred until the user has accepted
+/// or rejected the preview, if one is needed.
 pub fn apply_edit_from_server(
     meta: EditorMeta,
     server_id: ServerId,
-    params: Params,
+    request: MethodCall,
     ctx: &mut Context,
-) -> Result<Value, jsonrpc_core::Error> {
-    let params: ApplyWorkspaceEditParams = params.parse()?;
-    let response = apply_edit(server_id, meta, None, params.edit, ctx);
-    Ok(serde_json::to_value(response).unwrap())
+) {
+    let params: ApplyWorkspaceEditParams = match request.params.parse() {
+        Ok(params) => params,
+        Err(err) => {
+            ctx.reply(server_id, request.id, Err(err));
+            return;
+        }
+    };
+    let id = request.id;
+    apply_edit_then(
+        server_id,
+        meta,
+        None,
+        params.edit,
+        ctx,
+        move |ctx, _meta, response| {
+            // The server may have exited while the preview was shown.
+            if ctx.language_servers.contains_key(&server_id) {
+                ctx.reply(server_id, id, Ok(serde_json::to_value(response).unwrap()));
+            }
+        },
+    );
+}
+
+#[derive(Clone, Debug)]
+pub struct EditorRenameFileParams {
+    pub new_path: String,
+}
+
+/// A file system change to report to servers that registered interest in it.
+pub enum FileOperationEvent {
+    Create(PathBuf),
+    Rename {
+        from: PathBuf,
+        to: PathBuf,
+        is_dir: bool,
+    },
+    Delete {
+        path: PathBuf,
+        is_dir: bool,
+    },
+}
+
+impl FileOperationEvent {
+    /// Must be called before applying the operation, while directories can still be told apart.
+    fn of(op: &ResourceOp) -> Self {
+        match op {
+            ResourceOp::Create(op) => FileOperationEvent::Create(uri_to_file_path(&op.uri)),
+            ResourceOp::Rename(op) => {
+                let from = uri_to_file_path(&op.old_uri);
+                let is_dir = from.is_dir();
+                FileOperationEvent::Rename {
+                    from,
+                    to: uri_to_file_path(&op.new_uri),
+                    is_dir,
+                }
+            }
+            ResourceOp::Delete(op) => {
+                let path = uri_to_file_path(&op.uri);
+                let is_dir = path.is_dir();
+                FileOperationEvent::Delete { path, is_dir }
+            }
+        }
+    }
+}
+
+fn file_operations(server: &ServerSettings) -> Option<&WorkspaceFileOperationsServerCapabilities> {
+    server
+        .capabilities
+        .as_ref()?
+        .workspace
+        .as_ref()?
+        .file_operations
+        .as_ref()
+}
+
+/// Returns the servers of the path's project whose file operation filters, as selected by
+/// `options`, match the path.
+fn servers_interested_in(
+    ctx: &Context,
+    path: &Path,
+    is_dir: bool,
+    options: impl Fn(
+        &WorkspaceFileOperationsServerCapabilities,
+    ) -> Option<&FileOperationRegistrationOptions>,
+) -> Vec<ServerId> {
+    let mut servers: Vec<ServerId> = ctx
+        .language_servers
+        .iter()
+        .filter(|(_, server)| {
+            server.roots.iter().any(|root| path.starts_with(root))
+                && file_operations(server)
+                    .and_then(&options)
+                    .is_some_and(|options| file_operation_filters_match(options, path, is_dir))
+        })
+        .map(|(server_id, _)| *server_id)
+        .collect();
+    servers.sort();
+    servers
+}
+
+fn file_operation_filters_match(
+    options: &FileOperationRegistrationOptions,
+    path: &Path,
+    is_dir: bool,
+) -> bool {
+    let Some(path) = path.to_str() else {
+        return false;
+    };
+    options.filters.iter().any(|filter| {
+        if filter
+            .scheme
+            .as_ref()
+            .is_some_and(|scheme| scheme != "file")
+        {
+            return false;
+        }
+        let pattern = &filter.pattern;
+        match pattern.matches {
+            Some(FileOperationPatternKind::File) if is_dir => return false,
+            Some(FileOperationPatternKind::Folder) if !is_dir => return false,
+            _ => (),
+        }
+        let match_options = glob::MatchOptions {
+            case_sensitive: !pattern
+                .options
+                .as_ref()
+                .and_then(|options| options.ignore_case)
+                .unwrap_or(false),
+            require_literal_separator: true,
+            require_literal_leading_dot: false,
+        };
+        expand_braces(&pattern.glob).iter().any(|glob| {
+            glob::Pattern::new(glob).is_ok_and(|pattern| pattern.matches_with(path, match_options))
+        })
+    })
+}
+
+/// Expands LSP glob alternatives like "**/*.{ts,js}", which the glob crate does not support.
+fn expand_braces(glob: &str) -> Vec<String> {
+    let Some(open) = glob.find('{') else {
+        return vec![glob.to_string()];
+    };
+    let Some(close) = glob[open..].find('}').map(|close| open + close) else {
+        return vec![glob.to_string()];
+    };
+    let (prefix, suffix) = (&glob[..open], &glob[close + 1..]);
+    glob[open + 1..close]
+        .split(',')
+        .flat_map(|alternative| expand_braces(&format!("{prefix}{alternative}{suffix}")))
+        .collect()
+}
+
+fn did_file_operation(event: FileOperationEvent, ctx: &mut Context) {
+    match event {
+        FileOperationEvent::Create(path) => {
+            let params = CreateFilesParams {
+                files: vec![FileCreate {
+                    uri: file_path_to_uri(&path).to_string(),
+                }],
+            };
+            for server_id in servers_interested_in(ctx, &path, false, |ops| ops.did_create.as_ref())
+            {
+                ctx.notify::<DidCreateFiles>(server_id, params.clone());
+            }
+        }
+        FileOperationEvent::Rename { from, to, is_dir } => {
+            let params = RenameFilesParams {
+                files: vec![FileRename {
+                    old_uri: file_path_to_uri(&from).to_string(),
+                    new_uri: file_path_to_uri(&to).to_string(),
+                }],
+            };
+            for server_id in
+                servers_interested_in(ctx, &from, is_dir, |ops| ops.did_rename.as_ref())
+            {
+                ctx.notify::<DidRenameFiles>(server_id, params.clone());
+            }
+        }
+        FileOperationEvent::Delete { path, is_dir } => {
+            let params = DeleteFilesParams {
+                files: vec![FileDelete {
+                    uri: file_path_to_uri(&path).to_string(),
+                }],
+            };
+            for server_id in
+                servers_interested_in(ctx, &path, is_dir, |ops| ops.did_delete.as_ref())
+            {
+                ctx.notify::<DidDeleteFiles>(server_id, params.clone());
+            }
+        }
+    }
+}
+
+/// Renames the current buffer's file. Servers may update references to the file first.
+pub fn rename_file(meta: EditorMeta, params: EditorRenameFileParams, ctx: &mut Context) {
+    let from = PathBuf::from(&meta.buffile);
+    // Relative paths are relative to the buffer's directory.
+    let to = from.parent().unwrap().join(&params.new_path);
+    if to.exists() {
+        let msg = format!("lsp-rename-file: {} already exists", to.display());
+        ctx.show_error(meta, msg);
+        return;
+    }
+
+    let rename_params = RenameFilesParams {
+        files: vec![FileRename {
+            old_uri: file_path_to_uri(&from).to_string(),
+            new_uri: file_path_to_uri(&to).to_string(),
+        }],
+    };
+    let req_params: HashMap<ServerId, Vec<RenameFilesParams>> =
+        servers_interested_in(ctx, &from, false, |ops| ops.will_rename.as_ref())
+            .into_iter()
+            .map(|server_id| (server_id, vec![rename_params.clone()]))
+            .collect();
+    if req_params.is_empty() {
+        finish_rename_file(meta, from, to, ctx);
+        return;
+    }
+    // A server that fails to respond has no edits, and must not prevent the rename.
+    ctx.call_to_completion::<WillRenameFiles, _>(
+        meta,
+        RequestParams::Each(req_params),
+        move |ctx: &mut Context, meta, results| {
+            let edits = results
+                .into_iter()
+                .filter_map(|(server_id, edit)| Some((server_id, edit.flatten()?)))
+                .collect();
+            apply_edits_then_rename_file(meta, edits, from, to, ctx);
+        },
+    );
+}
+
+/// Moves the file only once the edits of all servers have been applied, so imports are not left
+/// pointing to the old path when an edit waits for confirmation or is rejected.
+fn apply_edits_then_rename_file(
+    meta: EditorMeta,
+    mut edits: Vec<(ServerId, WorkspaceEdit)>,
+    from: PathBuf,
+    to: PathBuf,
+    ctx: &mut Context,
+) {
+    let Some((server_id, edit)) = edits.pop() else {
+        finish_rename_file(meta, from, to, ctx);
+        return;
+    };
+    apply_edit_then(
+        server_id,
+        meta,
+        None,
+        edit,
+        ctx,
+        move |ctx, meta, response| {
+            if response.applied {
+                apply_edits_then_rename_file(meta, edits, from, to, ctx)
+            }
+        },
+    );
+}
+
+fn finish_rename_file(meta: EditorMeta, from: PathBuf, to: PathBuf, ctx: &mut Context) {
+    let result = match to.parent() {
+        Some(parent) => fs::create_dir_all(parent),
+        None => Ok(()),
+    }
+    .and_then(|()| fs::rename(&from, &to));
+    if let Err(err) = result {
+        let msg = format!("lsp-rename-file: failed to rename file: {}", err);
+        ctx.show_error(meta, msg);
+        return;
+    }
+    let command = format!(
+        "evaluate-commands -buffer {} %{{
+             lsp-did-close
+             rename-buffer -file {}
+             lsp-did-open
+         }}",
+        editor_quote(&meta.buffile),
+        editor_quote(to.to_str().unwrap()),
+    );
+    ctx.exec(meta, command);
+    did_file_operation(
+        FileOperationEvent::Rename {
+            from,
+            to,
+            is_dir: false,
+        },
+        ctx,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expand_braces_alternatives() {
+        assert_eq!(expand_braces("**/*.rs"), vec!["**/*.rs"]);
+        assert_eq!(expand_braces("**/*.{ts,js}"), vec!["**/*.ts", "**/*.js"]);
+        assert_eq!(
+            expand_braces("{src,lib}/*.{c,h}"),
+            vec!["src/*.c", "src/*.h", "lib/*.c", "lib/*.h"]
+        );
+    }
+
+    #[test]
+    fn file_operation_filters_match_globs() {
+        let options = FileOperationRegistrationOptions {
+            filters: vec![FileOperationFilter {
+                scheme: Some("file".to_string()),
+                pattern: FileOperationPattern {
+                    glob: "**/*.{rs,toml}".to_string(),
+                    matches: Some(FileOperationPatternKind::File),
+                    options: None,
+                },
+            }],
+        };
+        assert!(file_operation_filters_match(
+            &options,
+            Path::new("/project/src/main.rs"),
+            false
+        ));
+        assert!(file_operation_filters_match(
+            &options,
+            Path::new("/project/Cargo.toml"),
+            false
+        ));
+        assert!(!file_operation_filters_match(
+            &options,
+            Path::new("/project/README.md"),
+            false
+        ));
+        assert!(!file_operation_filters_match(
+            &options,
+            Path::new("/project/src.rs"),
+            true
+        ));
+    }
+
+    #[test]
+    fn change_groups_by_annotation_label() {
+        let uri: Uri = "file:///project/main.rs".parse().unwrap();
+        let text_edit = |line| TextEdit {
+            range: Range::new(Position::new(line, 0), Position::new(line, 1)),
+            new_text: "x".to_string(),
+        };
+        let annotated = |line, id: &str| {
+            OneOf::Right(AnnotatedTextEdit {
+                text_edit: text_edit(line),
+                annotation_id: id.to_string(),
+            })
+        };
+        let annotation = |label: &str, needs_confirmation| ChangeAnnotation {
+            label: label.to_string(),
+            needs_confirmation: Some(needs_confirmation),
+            description: None,
+        };
+        let edit = WorkspaceEdit {
+            changes: None,
+            document_changes: Some(DocumentChanges::Edits(vec![TextDocumentEdit {
+                text_document: OptionalVersionedTextDocumentIdentifier { uri, version: None },
+                edits: vec![
+                    OneOf::Left(text_edit(0)),
+                    annotated(1, "a"),
+                    annotated(2, "b"),
+                    annotated(3, "c"),
+                ],
+            }])),
+            change_annotations: Some(HashMap::from([
+                ("a".to_string(), annotation("source", false)),
+                ("b".to_string(), annotation("generated", true)),
+                ("c".to_string(), annotation("source", false)),
+            ])),
+        };
+        assert!(needs_confirmation(&edit));
+
+        let groups = change_groups(&edit);
+        let summary: Vec<_> = groups
+            .iter()
+            .map(|group| (group.label.as_deref(), group.selected))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (None, true),
+                (Some("source"), true),
+                (Some("generated"), false)
+            ]
+        );
+
+        let selected = select_changes(&edit, &[Some("source")]);
+        let Some(DocumentChanges::Edits(edits)) = selected.document_changes else {
+            panic!("expected text document edits");
+        };
+        let lines: Vec<_> = edits[0]
+            .edits
+            .iter()
+            .map(|edit| edit.as_ref().range.start.line)
+            .collect();
+        assert_eq!(lines, vec![1, 3]);
+    }
+
+    #[test]
+    fn rename_file_despite_will_rename_failure() {
+        let dir = std::env::temp_dir().join(format!("kak-lsp-rename-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let from = dir.join("old.rs");
+        fs::write(&from, "").unwrap();
+
+        let mut ctx = Context::new(
+            SessionId("session".to_string()),
+            crate::editor_transport::mock_to_editor(),
+            Config::default(),
+        );
+        let will_rename = FileOperationRegistrationOptions {
+            filters: vec![FileOperationFilter {
+                scheme: None,
+                pattern: FileOperationPattern {
+                    glob: "**/*.rs".to_string(),
+                    matches: None,
+                    options: None,
+                },
+            }],
+        };
+        let capabilities = ServerCapabilities {
+            workspace: Some(WorkspaceServerCapabilities {
+                workspace_folders: None,
+                file_operations: Some(WorkspaceFileOperationsServerCapabilities {
+                    will_rename: Some(will_rename),
+                    ..Default::default()
+                }),
+            }),
+            ..Default::default()
+        };
+        let (server_id, messages) = ctx.add_mock_server(dir.to_str().unwrap(), capabilities);
+        // Servers of other projects are not asked.
+        ctx.add_mock_server(
+            "/elsewhere",
+            ctx.server(server_id).capabilities.clone().unwrap(),
+        );
+
+        let meta = EditorMeta {
+            buffile: from.to_str().unwrap().to_string(),
+            ..Default::default()
+        };
+        let params = EditorRenameFileParams {
+            new_path: "new.rs".to_string(),
+        };
+        rename_file(meta, params, &mut ctx);
+        assert_eq!(ctx.response_waitlist.len(), 1);
+        let timeout = std::time::Duration::from_secs(1);
+        let Ok(ServerMessage::Request(jsonrpc_core::Call::MethodCall(call))) =
+            messages.recv_timeout(timeout)
+        else {
+            panic!("expected a willRenameFiles request");
+        };
+        assert_eq!(call.method, WillRenameFiles::METHOD);
+        assert!(from.exists());
+
+        let failure = jsonrpc_core::Failure {
+            jsonrpc: Some(jsonrpc_core::Version::V2),
+            error: jsonrpc_core::Error::internal_error(),
+            id: call.id,
+        };
+        let _ = crate::controller::process_server_response(
+            &mut ctx,
+            server_id,
+            jsonrpc_core::Output::Failure(failure),
+        );
+        let renamed = !from.exists() && dir.join("new.rs").exists();
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(renamed);
+    }
+
+    #[test]
+    fn apply_edit_from_server_waits_for_the_preview() {
+        let mut ctx = Context::new(
+            SessionId("session".to_string()),
+            crate::editor_transport::mock_to_editor(),
+            Config::default(),
+        );
+        let (server_id, messages) = ctx.add_mock_server("/project", Default::default());
+        let meta = EditorMeta {
+            servers: vec![server_id],
+            language_server: HashMap::from([(
+                ctx.server(server_id).name.clone(),
+                serde_json::from_value(serde_json::json!({"root": "/project"})).unwrap(),
+            )]),
+            ..Default::default()
+        };
+        let apply_edit_request = |id| {
+            let edit = WorkspaceEdit {
+                changes: None,
+                // Annotations of text edits do not survive deserialization, use a file creation.
+                document_changes: Some(DocumentChanges::Operations(vec![
+                    DocumentChangeOperation::Op(ResourceOp::Create(CreateFile {
+                        uri: "file:///project/new.rs".parse().unwrap(),
+                        options: None,
+                        annotation_id: Some("a".to_string()),
+                    })),
+                ])),
+                change_annotations: Some(HashMap::from([(
+                    "a".to_string(),
+                    ChangeAnnotation {
+                        label: "generated".to_string(),
+                        needs_confirmation: Some(true),
+                        description: None,
+                    },
+                )])),
+            };
+            let params = ApplyWorkspaceEditParams { label: None, edit };
+            MethodCall {
+                jsonrpc: Some(jsonrpc_core::Version::V2),
+                method: ApplyWorkspaceEdit::METHOD.to_string(),
+                params: Params::Map(
+                    serde_json::to_value(params)
+                        .unwrap()
+                        .as_object()
+                        .unwrap()
+                        .clone(),
+                ),
+                id: jsonrpc_core::Id::Num(id),
+            }
+        };
+        let timeout = std::time::Duration::from_secs(1);
+        let next_response = || match messages.recv_timeout(timeout) {
+            Ok(ServerMessage::Response(jsonrpc_core::Output::Success(success))) => {
+                let response: ApplyWorkspaceEditResponse =
+                    serde_json::from_value(success.result).unwrap();
+                (success.id, response.applied)
+            }
+            _ => panic!("expected a response"),
+        };
+
+        apply_edit_from_server(meta.clone(), server_id, apply_edit_request(1), &mut ctx);
+        assert!(ctx.pending_workspace_edit.is_some());
+        // A second preview is refused right away, without replacing the pending one.
+        apply_edit_from_server(meta.clone(), server_id, apply_edit_request(2), &mut ctx);
+        assert_eq!(next_response(), (jsonrpc_core::Id::Num(2), false));
+        assert!(messages.is_empty());
+
+        let params = EditorWorkspaceEditPreviewResponse { accept: false };
+        workspace_edit_preview_respond(meta, params, &mut ctx);
+        assert_eq!(next_response(), (jsonrpc_core::Id::Num(1), false));
+        assert!(ctx.pending_workspace_edit.is_none());
+    }
 }